@@ -0,0 +1,179 @@
+//! Black bars detected in the input file, shown on a frame of the video before the convertion.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use eframe::egui::{self, *};
+
+use crate::MediaInfo;
+use crate::engine::crop::{detect_crop, CropRect};
+use crate::engine::frames::{extract_frame, Frame};
+use crate::engine::runner::SystemRunner;
+use crate::engine::trim::Trim;
+
+const PREVIEW_HEIGHT: usize = 180;
+
+/// Result of the detection, tagged with the generation of the input it was detected in
+type Detection = (u64, Option<(CropRect, Option<Frame>)>);
+
+enum DetectionState {
+    Idle,
+    Running,
+    Failed,
+    Done {
+        rect: CropRect,
+        /// Size of the displayed picture of the input
+        source_size: (u32, u32),
+        frame: Option<TextureHandle>,
+    },
+}
+
+pub(super) struct CropPreview {
+    /// Input the black bars are detected in
+    input_path: String,
+    /// Increased whenever the input changes, so the detection of the previous input is dropped
+    generation: u64,
+    state: DetectionState,
+
+    detection_tx: Sender<Detection>,
+    detection_rx: Receiver<Detection>,
+}
+
+impl Default for CropPreview {
+    fn default() -> Self {
+        let (detection_tx, detection_rx) = mpsc::channel();
+        Self {
+            input_path: String::new(),
+            generation: 0,
+            state: DetectionState::Idle,
+            detection_tx,
+            detection_rx,
+        }
+    }
+}
+
+impl CropPreview {
+    /// Crop detected in the `input_path`, None until the detection is done
+    pub(super) fn detected(&self, input_path: &str) -> Option<CropRect> {
+        match self.state {
+            DetectionState::Done { rect, .. } if self.input_path == input_path => Some(rect),
+            _ => None,
+        }
+    }
+
+    /// Detects the black bars and decodes the frame in the middle of the trimmed part on a separate thread
+    fn spawn_detection(&mut self, ctx: &egui::Context, media_info: &MediaInfo, trim: &Trim) {
+        self.generation += 1;
+        self.state = DetectionState::Running;
+
+        let ctx = ctx.clone();
+        let detection_tx = self.detection_tx.clone();
+        let input_path = self.input_path.clone();
+        let media_info = media_info.clone();
+        let trim = trim.clone();
+        let generation = self.generation;
+
+        std::thread::spawn(move || {
+            let result = detect_crop(&SystemRunner, &input_path, &media_info, &trim).map(|rect| {
+                let duration = media_info.duration().unwrap_or(0);
+                let start = trim.start.unwrap_or(0);
+                let end = trim.end.map_or(duration, |end| end.min(duration));
+                let time = start + end.saturating_sub(start) / 2;
+
+                (rect, extract_frame(&SystemRunner, &input_path, &media_info, time, PREVIEW_HEIGHT))
+            });
+
+            let _ = detection_tx.send((generation, result));
+            ctx.request_repaint();
+        });
+    }
+
+    fn receive_detection(&mut self, ctx: &egui::Context, media_info: &MediaInfo) {
+        for (generation, result) in self.detection_rx.try_iter() {
+            if generation != self.generation {
+                continue;
+            }
+
+            let source_size = media_info.first_video().and_then(|video| video.display_size());
+            self.state = match (result, source_size) {
+                (Some((rect, frame)), Some(source_size)) => {
+                    let frame = frame.map(|frame| {
+                        let image = ColorImage::from_rgba_unmultiplied([ frame.width, frame.height ], &frame.rgba);
+                        ctx.load_texture("crop-preview", image, TextureOptions::LINEAR)
+                    });
+                    DetectionState::Done { rect, source_size, frame }
+                }
+                _ => DetectionState::Failed,
+            };
+        }
+    }
+
+    /// Shows the detected crop of the `input_path`, the detection starts as soon as the input is shown.
+    /// The `trim` selects the part of the media the bars are detected in.
+    pub(super) fn show(&mut self, ui: &mut egui::Ui, input_path: &str, media_info: &MediaInfo, trim: &Trim) {
+        if self.input_path != input_path {
+            self.input_path = String::from(input_path);
+            self.generation += 1;
+            self.state = DetectionState::Idle;
+        }
+
+        if media_info.first_video().is_none() {
+            return;
+        }
+
+        if matches!(self.state, DetectionState::Idle) {
+            self.spawn_detection(ui.ctx(), media_info, trim);
+        }
+        self.receive_detection(ui.ctx(), media_info);
+
+        let mut detect_again = false;
+        ui.horizontal(|ui| {
+            match &self.state {
+                DetectionState::Idle | DetectionState::Running => {
+                    ui.spinner();
+                    ui.label("Detecting the black bars...");
+                }
+                DetectionState::Failed => {
+                    ui.colored_label(ui.visuals().warn_fg_color, "Failed to detect the black bars of the video.");
+                }
+                DetectionState::Done { rect, source_size: (width, height), .. } if rect.is_full(*width, *height) => {
+                    ui.label("No black bars were found, the video is not going to be cropped.");
+                }
+                DetectionState::Done { rect, source_size: (width, height), .. } => {
+                    ui.label(format!("Cropped from {width}x{height} to {}x{} (offset {}, {})", rect.width, rect.height, rect.x, rect.y));
+                }
+            }
+
+            let running = matches!(self.state, DetectionState::Running);
+            detect_again = ui.add_enabled(!running, Button::new("Detect again"))
+                .on_hover_text("The bars are detected in the trimmed part of the media")
+                .clicked();
+        });
+
+        if detect_again {
+            self.spawn_detection(ui.ctx(), media_info, trim);
+        }
+
+        if let DetectionState::Done { rect, source_size, frame: Some(texture) } = &self.state {
+            add_preview(ui, texture, rect, *source_size);
+        }
+    }
+}
+
+/// Frame of the video with the cropped off parts darkened and the kept part outlined
+fn add_preview(ui: &mut egui::Ui, texture: &TextureHandle, crop: &CropRect, (width, height): (u32, u32)) {
+    let (rect, _) = ui.allocate_exact_size(texture.size_vec2(), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.image(texture.id(), rect, Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0)), Color32::WHITE);
+
+    // The frame is scaled down, so the crop is scaled together with it
+    let scale = vec2(rect.width() / width.max(1) as f32, rect.height() / height.max(1) as f32);
+    let min = rect.min + vec2(crop.x as f32 * scale.x, crop.y as f32 * scale.y);
+    let kept = Rect::from_min_size(min, vec2(crop.width as f32 * scale.x, crop.height as f32 * scale.y));
+
+    let shade = Color32::from_black_alpha(160);
+    painter.rect_filled(Rect::from_min_max(rect.min, pos2(rect.right(), kept.top())), 0.0, shade);
+    painter.rect_filled(Rect::from_min_max(pos2(rect.left(), kept.bottom()), rect.max), 0.0, shade);
+    painter.rect_filled(Rect::from_min_max(pos2(rect.left(), kept.top()), pos2(kept.left(), kept.bottom())), 0.0, shade);
+    painter.rect_filled(Rect::from_min_max(pos2(kept.right(), kept.top()), pos2(rect.right(), kept.bottom())), 0.0, shade);
+    painter.rect_stroke(kept, 0.0, Stroke::new(1.0, ui.visuals().selection.stroke.color));
+}
@@ -1,9 +1,9 @@
-use std::{path::{Path, PathBuf}, time::Duration};
+use std::time::Duration;
 use eframe::egui::*;
-use egui_toast::{Toast, ToastOptions};
 
 use crate::{*, popup::LogKind};
 use super::*;
+use crate::engine::batch::default_batch_output_dir;
 
 impl eframe::App for Compressor {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
@@ -11,8 +11,6 @@ impl eframe::App for Compressor {
         ctx.set_pixels_per_point(GUI_SCALE);
         self.popup.show(ctx);
 
-        self.receive_log_messages();
-
         if let Some(last_update_time) = self.last_state_update {
             let time_now = Instant::now();
             if time_now - last_update_time >= Duration::from_secs(1) {
@@ -27,11 +25,7 @@ impl eframe::App for Compressor {
         // let winfo = _frame.info().window_info;
         // println!("DEBUGGING: {winfo:?}");
 
-        if let Some(rx) = &self.work_finished_rx {
-            if let Ok(()) = rx.try_recv() {
-                self.is_working = false;
-            }
-        }
+        self.poll_running_job();
 
         if self.should_exit && !self.is_working {
             self.save_cache();
@@ -40,13 +34,11 @@ impl eframe::App for Compressor {
             std::process::exit(0);
         }
 
-        match &self.work_progress_rx {
-            Some(work_progress_rx) if self.is_working => {
-                if let Ok(new_progress) = work_progress_rx.try_recv() {
-                    self.progress = new_progress;
-                }
-            }
-            _ => {}
+        self.start_next_job();
+
+        // Keep polling the working thread even when the user is not moving the mouse
+        if self.is_working {
+            ctx.request_repaint_after(Duration::from_millis(100));
         }
 
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -55,11 +47,36 @@ impl eframe::App for Compressor {
 
             if matches!(self.selected_mode, AppMode::Advanced) {
                 self.add_advanced_settings(ui);
+            } else if matches!(self.selected_mode, AppMode::Thumbnail) {
+                self.add_thumbnail_settings(ui);
+            } else if matches!(self.selected_mode, AppMode::Animation) {
+                self.add_animation_settings(ui);
             } else {
                 self.add_quality_settings(ui);
             }
             ui.separator();
 
+            if !matches!(self.selected_mode, AppMode::Download | AppMode::Thumbnail) {
+                self.add_trim_settings(ui);
+                ui.separator();
+            }
+
+            if matches!(self.selected_mode, AppMode::Audio | AppMode::Video) {
+                self.add_loudness_settings(ui);
+                ui.separator();
+            }
+
+            if matches!(self.selected_mode, AppMode::Video) {
+                self.add_frame_rate_settings(ui);
+                ui.separator();
+
+                self.add_crop_settings(ui);
+                ui.separator();
+
+                self.add_subtitle_settings(ui);
+                ui.separator();
+            }
+
             self.add_custom_size_picker(ui);
             ui.separator();
 
@@ -70,50 +87,70 @@ impl eframe::App for Compressor {
 
             ui.add_space(10.0);
 
-            if !self.is_working {
-                ui.label("Waiting for a convertion/download job...");
-            } else if matches!(self.selected_mode, AppMode::Download) {
-                ui.label(format!("Downloading in progress: [{:.2}%]", (self.progress * 100.0)));
-            } else {
-                ui.label(format!("Convertion in progress: [{:.2}%]", (self.progress * 100.0)));
-            };
+            let percent = self.progress.fraction * 100.0;
+            let details = self.progress.details();
+
+            let running_job = self.job_queue.iter().find(|entry| entry.status == JobStatus::Running);
+            ui.horizontal(|ui| {
+                match running_job.map(|entry| &entry.job) {
+                    None => ui.label("Waiting for a convertion/download job..."),
+                    Some(CompressorJob::Download(_)) => ui.label(format!("Downloading in progress: [{percent:.2}%]")),
+                    Some(CompressorJob::Dual(_)) if percent < 50.0 => {
+                        ui.label(format!("Downloading (1/2) in progress: [{percent:.2}%]"))
+                    }
+                    Some(CompressorJob::Dual(_)) => ui.label(format!("Converting (2/2) in progress: [{percent:.2}%]")),
+                    Some(CompressorJob::Convert(_))  => ui.label(format!("Convertion in progress: [{percent:.2}%]")),
+                };
+
+                if self.is_working && !details.is_empty() {
+                    ui.add(egui::Separator::default().vertical());
+                    ui.label(details);
+                }
+            });
 
             ui.add_enabled(
-                self.is_working, egui::ProgressBar::new(self.progress).animate(self.is_working)
+                self.is_working, egui::ProgressBar::new(self.progress.fraction).animate(self.is_working)
             );
+
+            let running_batch = self.job_queue.iter()
+                .find(|entry| entry.status == JobStatus::Running)
+                .and_then(|entry| entry.batch);
+
+            if let Some(batch) = running_batch {
+                let (done, total, fraction) = self.batch_progress(batch);
+                ui.label(format!("Directory convertion: {done} of {total} files done, [{:.2}%] overall", fraction * 100.0));
+                ui.add(egui::ProgressBar::new(fraction));
+            }
+
+            if !self.job_queue.is_empty() {
+                ui.separator();
+                self.add_job_queue(ui);
+            }
         });
     }
 }
 
 impl Compressor {
-    pub(super) fn try_output_from_input_path(&mut self) -> Option<String> {
-        let extension = match self.selected_mode {
-            AppMode::Audio    => "mp3",
-            AppMode::Video    => "mp4",
-            AppMode::Download => return None,
-            AppMode::Advanced => return None,
-            // AppMode::DualMode => return None,
-        };
-
-        let input_path = Path::new(&self.input_path);
-        if !input_path.exists() || input_path.is_dir() {
-            return None;
+    pub(super) fn try_output_from_input_path(&self) -> Option<String> {
+        if self.is_batch_input() {
+            let output_dir = default_batch_output_dir(std::path::Path::new(self.input_path.trim()))?;
+            return Some(String::from(output_dir.to_string_lossy()));
         }
 
-        let Some(input_filename) = input_path.file_stem() else {
-            return None;
-        };
-
-        let input_filename = input_filename.to_str().unwrap();
-        let mut output = PathBuf::from(input_path.parent().unwrap());
-        output.push(format!("{input_filename}-new.{extension}"));
+        // The name of the output is known only once the media is downloaded
+        if self.dual_mode {
+            return Some(String::from(default_download_dir()?.to_string_lossy()));
+        }
 
-        Some(String::from(output.to_string_lossy()))
+        output_from_input_path(&self.input_path, &self.output_extension()?)
     }
 
     fn add_custom_size_picker(&mut self, ui: &mut egui::Ui) {
+        // Bitrates in the Advanced mode are always selected by hand
+        let enabled = !matches!(self.selected_mode, AppMode::Download | AppMode::Advanced | AppMode::Thumbnail);
+
         let btn = ui.add_enabled(
-            !self.is_working && !matches!(self.selected_mode, AppMode::Download),
+            enabled,
             RadioButton::new(
                 self.use_output_file_size, 
                 "Approximate size of the output file (automatic quality selection):"
@@ -126,43 +163,347 @@ impl Compressor {
 
         ui.horizontal(|ui| {
 
-            ui.add_enabled_ui(
-                self.use_output_file_size && !matches!(self.selected_mode, AppMode::Download) && 
-                !self.is_working, |ui| {
+            ui.add_enabled_ui(self.use_output_file_size && enabled, |ui| {
                 ui.label("Video / Audio bitrate ratio:");
-                ui.add(Slider::new(&mut self.bitrate_ratio, 0.0..=20.0).text("/ 1.0"));
+                ui.add(Slider::new(&mut self.bitrate_ratio, 1.0..=20.0).text("/ 1.0"));
                 ui.add(egui::Separator::default().vertical());
                 ui.label("Size in MB:");
                 ui.add(TextEdit::singleline(&mut self.output_file_size).desired_width(f32::INFINITY));
             });
         });
 
+        // The animations have no bitrate, they are always verified and encoded again with a lower frame rate and width
+        let animation = matches!(self.selected_mode, AppMode::Animation);
+        ui.add_enabled_ui(self.use_output_file_size && enabled && !animation, |ui| {
+            ui.checkbox(&mut self.verify_output_size, "Verify the size and encode again when the output is too large");
+        });
+
+        if self.use_output_file_size && enabled {
+            self.update_input_media_info(ui.ctx());
+            if let Some(bitrates) = self.target_bitrates_label() {
+                ui.label(bitrates);
+            }
+        }
+    }
+
+    fn add_trim_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.trim_enabled, "Trim:");
+
+            ui.add_enabled_ui(self.trim_enabled, |ui| {
+                ui.label("Start:");
+                ui.add(TextEdit::singleline(&mut self.trim_start).hint_text("00:00:00").desired_width(80.0));
+
+                let end_label = |by_duration: bool| if by_duration { "Duration:" } else { "End:" };
+                ComboBox::from_id_source("trim_end").selected_text(end_label(self.trim_by_duration)).show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.trim_by_duration, false, end_label(false));
+                    ui.selectable_value(&mut self.trim_by_duration, true, end_label(true));
+                });
+                ui.add(TextEdit::singleline(&mut self.trim_end).hint_text("Until the end").desired_width(80.0));
+
+                ui.add(egui::Separator::default().vertical());
+                ComboBox::from_id_source("cut_mode").selected_text(self.cut_mode.label()).show_ui(ui, |ui| {
+                    for mode in [ CutMode::Accurate, CutMode::Fast ] {
+                        ui.selectable_value(&mut self.cut_mode, mode, mode.label());
+                    }
+                });
+            });
+        });
+
+        if !self.trim_enabled {
+            return;
+        }
+
+        if let Err(message) = self.selected_trim() {
+            ui.colored_label(ui.visuals().warn_fg_color, message);
+        }
+
+        // The timeline needs a single input file
+        if self.is_batch_input() || self.dual_mode {
+            return;
+        }
+
+        self.update_input_media_info(ui.ctx());
+        let Some(media_info) = &self.input_media_info else {
+            return;
+        };
+        self.timeline.load(ui.ctx(), &self.input_path, media_info);
+
+        let duration = media_info.duration().unwrap_or(0);
+        let mut start = parse_time(&self.trim_start).unwrap_or(0);
+        let mut end = match parse_time(&self.trim_end) {
+            Some(duration) if self.trim_by_duration => start + duration,
+            Some(end) => end,
+            None => duration,
+        };
+
+        if self.timeline.show(ui, &mut start, &mut end) {
+            self.trim_start = format_time(start);
+            self.trim_end = format_time(if self.trim_by_duration { end - start } else { end });
+        }
+    }
+
+    fn add_loudness_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.loudnorm_enabled, "Normalize loudness:");
+
+            ui.add_enabled_ui(self.loudnorm_enabled, |ui| {
+                let loudnorm = &mut self.loudnorm;
+                ui.label("Target:");
+                ui.add(DragValue::new(&mut loudnorm.integrated).speed(0.1).clamp_range(INTEGRATED_RANGE).suffix(" LUFS"));
+                ui.label("True peak:");
+                ui.add(DragValue::new(&mut loudnorm.true_peak).speed(0.1).clamp_range(TRUE_PEAK_RANGE).suffix(" dBTP"));
+                ui.label("Range:");
+                ui.add(DragValue::new(&mut loudnorm.range).speed(0.1).clamp_range(LOUDNESS_RANGE).suffix(" LU"));
+
+                if ui.button("Reset").clicked() {
+                    *loudnorm = Loudnorm::default();
+                }
+            });
+        });
+
+        if self.loudnorm_enabled && self.trim_enabled && self.cut_mode == CutMode::Fast {
+            ui.colored_label(ui.visuals().warn_fg_color, "Fast cuts do not encode the media, so the loudness can not be normalized.");
+        }
+    }
+
+    fn add_frame_rate_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Frame rate:");
+
+            let value = &mut self.frame_rate_value;
+            let target = &mut self.frame_rate.target;
+            ComboBox::from_id_source("frame_rate").selected_text(target.label()).show_ui(ui, |ui| {
+                let targets = [ FrameRateTarget::Keep, FrameRateTarget::Cap(*value), FrameRateTarget::Convert(*value) ];
+                for option in targets {
+                    if ui.selectable_label(option.label() == target.label(), option.label()).clicked() {
+                        *target = option;
+                    }
+                }
+            });
+
+            ui.add_enabled_ui(*target != FrameRateTarget::Keep, |ui| {
+                let fps = DragValue::new(value).speed(0.1).clamp_range(1.0..=240.0).suffix(" fps");
+                if ui.add(fps).changed() {
+                    *target = match *target {
+                        FrameRateTarget::Keep       => FrameRateTarget::Keep,
+                        FrameRateTarget::Cap(_)     => FrameRateTarget::Cap(*value),
+                        FrameRateTarget::Convert(_) => FrameRateTarget::Convert(*value),
+                    };
+                }
+
+                add_codec_selector(ui, "frame_rate_method", &mut self.frame_rate.method, FRAME_RATE_METHODS, FrameRateMethod::label);
+            });
+
+            ui.checkbox(&mut self.frame_rate.constant, "Constant frame rate")
+                .on_hover_text("Variable frame rate videos (screen and phone recordings) may go out of sync in some players");
+        });
+
+        if !self.is_batch_input() && !self.dual_mode {
+            self.update_input_media_info(ui.ctx());
+            let source_fps = self.input_media_info.as_ref()
+                .and_then(|media_info| media_info.first_video())
+                .and_then(describe_frame_rate);
+
+            if let Some(source_fps) = source_fps {
+                ui.label(format!("Source frame rate: {source_fps}"));
+            }
+        }
+
+        if self.frame_rate != FrameRate::default() && self.trim_enabled && self.cut_mode == CutMode::Fast {
+            ui.colored_label(ui.visuals().warn_fg_color, "Fast cuts do not encode the media, so the frame rate can not be changed.");
+        }
+    }
+
+    fn add_crop_settings(&mut self, ui: &mut egui::Ui) {
+        let single_input = !self.is_batch_input() && !self.dual_mode;
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.crop_enabled, "Crop black bars")
+                .on_hover_text("The bars are cropped off before the video is scaled");
+
+            if self.crop_enabled && !single_input {
+                ui.label("The bars are detected right before every convertion");
+            }
+        });
+
+        if !self.crop_enabled {
+            return;
+        }
+
+        if self.trim_enabled && self.cut_mode == CutMode::Fast {
+            ui.colored_label(ui.visuals().warn_fg_color, "Fast cuts do not encode the media, so the black bars can not be cropped.");
+        }
+
+        if !single_input {
+            return;
+        }
+
+        let trim = if self.trim_enabled { self.selected_trim().unwrap_or_default() } else { Trim::default() };
+        self.update_input_media_info(ui.ctx());
+        if let Some(media_info) = &self.input_media_info {
+            self.crop_preview.show(ui, &self.input_path, media_info, &trim);
+        }
+    }
+
+    fn add_subtitle_settings(&mut self, ui: &mut egui::Ui) {
+        // Tracks are listed only for a single input file, the batches and downloads can only get the subtitle file
+        let tracks: Vec<(usize, String, bool)> = if self.is_batch_input() || self.dual_mode {
+            Vec::new()
+        } else {
+            self.update_input_media_info(ui.ctx());
+            self.input_media_info.iter()
+                .flat_map(|media_info| media_info.subtitle_streams())
+                .map(|stream| (stream.index, track_label(stream), is_text_subtitle(stream)))
+                .collect()
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Burn subtitles:");
+
+            let selected_text = match &self.burn_subtitles {
+                BurnIn::None => String::from("None"),
+                BurnIn::Track(index) => format!("#{index}"),
+                BurnIn::File(_) => String::from("Subtitle file"),
+            };
+
+            ComboBox::from_id_source("burn_subtitles").selected_text(selected_text).show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.burn_subtitles, BurnIn::None, "None");
+
+                // Only the text subtitles can be burned in
+                for (index, label, _) in tracks.iter().filter(|(_, _, is_text)| *is_text) {
+                    ui.selectable_value(&mut self.burn_subtitles, BurnIn::Track(*index), label);
+                }
+
+                let is_file = matches!(self.burn_subtitles, BurnIn::File(_));
+                if ui.selectable_label(is_file, "Subtitle file").clicked() && !is_file {
+                    self.burn_subtitles = BurnIn::File(String::new());
+                }
+            });
+
+            if let BurnIn::File(path) = &mut self.burn_subtitles {
+                if ui.button("File selection").clicked() {
+                    let res = rfd::FileDialog::new()
+                        .add_filter("Subtitles", &[ "srt", "ass", "ssa", "vtt" ])
+                        .pick_file();
+
+                    if let Some(file) = res {
+                        *path = file.to_string_lossy().to_string();
+                    }
+                }
+                ui.add(TextEdit::singleline(path).hint_text("Path to a .srt or .ass file").desired_width(f32::INFINITY));
+            }
+        });
+
+        for (index, label, is_text) in &tracks {
+            ui.horizontal(|ui| {
+                // mp4 and webm can not store the image-based subtitles
+                ui.add_enabled_ui(*is_text, |ui| add_track_checkbox(ui, &mut self.keep_subtitles, *index, "Keep"));
+                add_track_checkbox(ui, &mut self.extract_subtitles, *index, "Extract");
+                ui.label(label);
+            });
+        }
+    }
+
+    /// Probes the input again whenever the input path changes. ffprobe runs on a separate thread (slow or
+    /// network files would freeze the GUI), the media info shows up once it is done.
+    fn update_input_media_info(&mut self, ctx: &egui::Context) {
+        for (path, media_info) in self.probe_rx.try_iter() {
+            // Results of the previous inputs are dropped
+            if path == self.probed_input_path {
+                self.input_media_info = media_info;
+                self.is_probing = false;
+            }
+        }
+
+        if self.probed_input_path == self.input_path {
+            return;
+        }
+
+        // Selected subtitle tracks belong to the previous input
+        self.keep_subtitles.clear();
+        self.extract_subtitles.clear();
+        if matches!(self.burn_subtitles, BurnIn::Track(_)) {
+            self.burn_subtitles = BurnIn::None;
+        }
+
+        self.probed_input_path = self.input_path.clone();
+        self.input_media_info = None;
+        self.is_probing = std::path::Path::new(self.input_path.trim()).is_file();
+        if !self.is_probing {
+            return;
+        }
+
+        let ctx = ctx.clone();
+        let probe_tx = self.probe_tx.clone();
+        let input_path = self.input_path.clone();
+        std::thread::spawn(move || {
+            let media_info = get_media_info(input_path.trim());
+            let _ = probe_tx.send((input_path, media_info));
+            ctx.request_repaint();
+        });
+    }
+
+    /// Bitrates the selected output size is going to be encoded with. None when the input is not a media file.
+    fn target_bitrates_label(&self) -> Option<String> {
+        let media_info = self.input_media_info.as_ref()?;
+
+        let job = match self.create_convertion_job(self.input_path.clone()) {
+            Ok(job) => job,
+            Err(message) => return Some(String::from(message)),
+        };
+
+        match job.target_bitrates(media_info) {
+            Ok(bitrates) => Some(format!("Target bitrates: {}", bitrates?)),
+            Err(message) => Some(String::from(message)),
+        }
+    }
+
+    /// Bitrates selected by the quality levels for the input file. None when nothing is encoded with a bitrate.
+    fn quality_bitrates_label(&self) -> Option<String> {
+        let media_info = self.input_media_info.as_ref()?;
+        let job = self.create_convertion_job(self.input_path.clone()).ok()?;
+
+        let bitrates = job.quality_bitrates(media_info);
+        if bitrates.video.is_none() && bitrates.audio.is_none() {
+            return None;
+        }
+        Some(format!("Bitrates: {bitrates}"))
     }
 
     fn add_top_options(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             let audio_btn = RadioButton::new(matches!(self.selected_mode, AppMode::Audio), "Audio");
-            if ui.add_enabled(!self.is_working, audio_btn).clicked() {
+            if ui.add(audio_btn).clicked() {
                 self.selected_mode = AppMode::Audio;
             }
 
             let video_btn = RadioButton::new(matches!(self.selected_mode, AppMode::Video), "Video");
-            if ui.add_enabled(!self.is_working, video_btn).clicked() {
+            if ui.add(video_btn).clicked() {
                 self.selected_mode = AppMode::Video;
             }
 
             let download_btn = RadioButton::new(matches!(self.selected_mode, AppMode::Download) || self.dual_mode, "Downloading");
-            if ui.add_enabled(!self.is_working && !self.dual_mode, download_btn).clicked() {
+            if ui.add_enabled(!self.dual_mode, download_btn).clicked() {
                 self.selected_mode = AppMode::Download;
             }
 
             let advanced_btn = RadioButton::new(matches!(self.selected_mode, AppMode::Advanced), "Advanced");
-            if ui.add_enabled(!self.is_working && !self.is_working, advanced_btn).clicked() {
+            if ui.add(advanced_btn).clicked() {
                 self.selected_mode = AppMode::Advanced;
             }
 
+            let thumbnail_btn = RadioButton::new(matches!(self.selected_mode, AppMode::Thumbnail), "Thumbnail");
+            if ui.add(thumbnail_btn).clicked() {
+                self.selected_mode = AppMode::Thumbnail;
+            }
+
+            let animation_btn = RadioButton::new(matches!(self.selected_mode, AppMode::Animation), "GIF / WebP");
+            if ui.add(animation_btn).clicked() {
+                self.selected_mode = AppMode::Animation;
+            }
+
             let dual_btn = RadioButton::new(self.dual_mode, "Dual Mode");
-            if ui.add_enabled(!self.is_working, dual_btn).clicked() {
+            if ui.add(dual_btn).clicked() {
                 if !self.dual_mode && matches!(self.selected_mode, AppMode::Download) {
                     self.selected_mode = AppMode::Audio;
                 }
@@ -172,18 +513,235 @@ impl Compressor {
         });
     }
 
-    fn add_advanced_settings(&mut self, _ui: &mut egui::Ui) {
-        // TODO
+    fn add_thumbnail_settings(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.radio_value(&mut self.contact_sheet, false, "Single frame");
+            ui.radio_value(&mut self.contact_sheet, true, "Contact sheet");
+
+            ui.add(egui::Separator::default().vertical());
+            ui.label("Format:");
+            add_codec_selector(ui, "image_format", &mut self.thumbnail_options.format, IMAGE_FORMATS, ImageFormat::label);
+
+            ui.add(egui::Separator::default().vertical());
+            ui.label("Width:");
+            ui.add(DragValue::new(&mut self.thumbnail_options.width).clamp_range(0..=7680).suffix(" px"))
+                .on_hover_text("0 keeps the width of the video");
+        });
+
+        if self.contact_sheet {
+            ui.horizontal(|ui| {
+                ui.label("Columns:");
+                ui.add(Slider::new(&mut self.sheet_columns, 1..=MAX_SHEET_SIZE));
+                ui.label("Rows:");
+                ui.add(Slider::new(&mut self.sheet_rows, 1..=MAX_SHEET_SIZE));
+                ui.checkbox(&mut self.sheet_timestamps, "Timestamps");
+            });
+        } else {
+            ui.horizontal(|ui| {
+                ui.label("Time:");
+                ui.add(TextEdit::singleline(&mut self.thumbnail_time).hint_text("Middle of the video").desired_width(120.0));
+            });
+
+            if let Err(message) = self.selected_thumbnail() {
+                ui.colored_label(ui.visuals().warn_fg_color, message);
+            }
+        }
+    }
+
+    fn add_animation_settings(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.animation_options;
+
+        ui.horizontal(|ui| {
+            ui.label("Format:");
+            add_codec_selector(ui, "animation_format", &mut options.format, ANIMATION_FORMATS, AnimationFormat::label);
+
+            ui.add(egui::Separator::default().vertical());
+            ui.label("Frame rate:");
+            ui.add(Slider::new(&mut options.fps, 1..=60).suffix(" fps"));
+
+            ui.add(egui::Separator::default().vertical());
+            ui.label("Width:");
+            ui.add(DragValue::new(&mut options.width).clamp_range(16..=3840).suffix(" px"))
+                .on_hover_text("Smaller videos are never scaled up");
+        });
+
+        ui.horizontal(|ui| {
+            // WebP keeps all of the colors, only the palette of the GIF needs the dithering
+            ui.add_enabled_ui(matches!(options.format, AnimationFormat::Gif), |ui| {
+                ui.label("Dithering:");
+                ComboBox::from_id_source("dither").selected_text(options.dither.label()).show_ui(ui, |ui| {
+                    for dither in DITHERS {
+                        // The Bayer scale is kept when the Bayer dithering is selected again
+                        let selected = dither.label() == options.dither.label();
+                        if ui.selectable_label(selected, dither.label()).clicked() && !selected {
+                            options.dither = *dither;
+                        }
+                    }
+                });
+
+                if let Dither::Bayer(scale) = &mut options.dither {
+                    ui.label("Scale:");
+                    ui.add(Slider::new(scale, 0..=5));
+                }
+            });
+
+            ui.add(egui::Separator::default().vertical());
+            ui.label("Plays:");
+            let plays = DragValue::new(&mut options.plays)
+                .clamp_range(0..=100)
+                .custom_formatter(|value, _| {
+                    if value == 0.0 { String::from("Loop forever") } else { format!("{value}x") }
+                });
+            ui.add(plays);
+        });
+
+        if self.use_output_file_size {
+            ui.label("The frame rate and the width get lowered until the animation fits the selected size.");
+        }
+    }
+
+    fn add_advanced_settings(&mut self, ui: &mut egui::Ui) {
+        let options = &mut self.advanced_options;
+
+        ui.horizontal(|ui| {
+            ui.vertical(|ui| {
+                ui.checkbox(&mut options.audio_enabled, "Audio");
+
+                ui.add_enabled_ui(options.audio_enabled, |ui| {
+                    // With the video enabled the audio codec is picked by the container of the video codec
+                    ui.add_enabled_ui(!options.video_enabled, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("Codec:");
+                            add_codec_selector(ui, "advanced_audio_codec", &mut self.audio_codec, AUDIO_CODECS, AudioCodec::label);
+                        });
+
+                        if !options.video_enabled && matches!(self.audio_codec, AudioCodec::Flac) {
+                            add_flac_compression(ui, &mut self.flac_compression);
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Bitrate:");
+                        let bitrate = DragValue::new(&mut options.audio_bitrate)
+                            .clamp_range(0..=640)
+                            .custom_formatter(|value, _| {
+                                if value == 0.0 { String::from("Original") } else { format!("{value} kbit/s") }
+                            });
+                        ui.add(bitrate);
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Sample rate:");
+                        let selected = match options.audio_sample_rate {
+                            0 => String::from("Original"),
+                            rate => format!("{rate} Hz"),
+                        };
+
+                        ComboBox::from_id_source("audio_sample_rate").selected_text(selected).show_ui(ui, |ui| {
+                            ui.selectable_value(&mut options.audio_sample_rate, 0, "Original");
+                            for rate in AUDIO_SAMPLE_RATES {
+                                ui.selectable_value(&mut options.audio_sample_rate, *rate, format!("{rate} Hz"));
+                            }
+                        });
+                    });
+                });
+            });
+
+            ui.add(egui::Separator::default().vertical());
+
+            ui.vertical(|ui| {
+                ui.checkbox(&mut options.video_enabled, "Video");
+
+                ui.add_enabled_ui(options.video_enabled, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Bitrate:");
+                        let bitrate = DragValue::new(&mut options.video_bitrate)
+                            .clamp_range(0..=200_000)
+                            .custom_formatter(|value, _| {
+                                if value == 0.0 { String::from("Use CRF") } else { format!("{value} kbit/s") }
+                            });
+                        ui.add(bitrate);
+                    });
+
+                    ui.add_enabled_ui(options.video_bitrate == 0, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("CRF:");
+                            ui.add(Slider::new(&mut options.crf, 0..=self.video_codec.max_crf()));
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        let original = |value: f64, _| {
+                            if value == 0.0 { String::from("Auto") } else { value.to_string() }
+                        };
+
+                        ui.label("Size:");
+                        ui.add(DragValue::new(&mut options.video_width).clamp_range(0..=8192).custom_formatter(original));
+                        ui.label("x");
+                        ui.add(DragValue::new(&mut options.video_height).clamp_range(0..=8192).custom_formatter(original));
+                    });
+                });
+            });
+
+            ui.add(egui::Separator::default().vertical());
+
+            ui.vertical(|ui| {
+                ui.add_enabled_ui(options.video_enabled, |ui| {
+                    ui.label("Encoder:");
+
+                    ui.horizontal(|ui| {
+                        ui.label("Codec:");
+                        if add_codec_selector(ui, "advanced_codec", &mut self.video_codec, VIDEO_CODECS, VideoCodec::label) {
+                            // The CRF scales of the codecs differ, so the codec default is a better start
+                            options.crf = self.video_codec.default_crf();
+                        }
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Preset:");
+                        ComboBox::from_id_source("advanced_preset").selected_text(options.preset).show_ui(ui, |ui| {
+                            for preset in ADVANCED_PRESET_STRINGS {
+                                ui.selectable_value(&mut options.preset, *preset, *preset);
+                            }
+                        });
+                    });
+
+                    ui.horizontal(|ui| {
+                        ui.label("Tune:");
+                        let label = |tune: &'static str| if tune.is_empty() { "none" } else { tune };
+                        ComboBox::from_id_source("advanced_tune").selected_text(label(options.tune)).show_ui(ui, |ui| {
+                            for tune in ADVANCED_TUNE_STRINGS {
+                                ui.selectable_value(&mut options.tune, *tune, label(tune));
+                            }
+                        });
+                    });
+                });
+            });
+        });
+
+        if let Err(message) = self.advanced_options.validate(self.video_codec, self.audio_codec) {
+            ui.colored_label(ui.visuals().warn_fg_color, message);
+        }
     }
 
     fn add_quality_settings(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             ui.vertical(|ui| {
+                let audio_mode = matches!(self.selected_mode, AppMode::Audio);
+                if audio_mode {
+                    ui.horizontal(|ui| {
+                        ui.label("Codec:");
+                        add_codec_selector(ui, "audio_codec", &mut self.audio_codec, AUDIO_CODECS, AudioCodec::label);
+                    });
+                }
+
                 ui.label("Audio quality: ");
 
+                // Lossless codecs keep the quality of the input
+                let lossless = audio_mode && self.audio_codec.is_lossless();
                 for (i, text) in QUALITY_GUI_LABELS.iter().enumerate() {
                     let resp = ui.add_enabled(
-                        !self.is_working && !matches!(self.selected_mode, AppMode::Download) && !self.use_output_file_size, 
+                        !matches!(self.selected_mode, AppMode::Download) && !self.use_output_file_size && !lossless, 
                         RadioButton::new(i == self.audio_quality as usize, *text)
                     );
 
@@ -191,6 +749,10 @@ impl Compressor {
                         self.audio_quality = Quality::from_usize(i);
                     }
                 }
+
+                if audio_mode && matches!(self.audio_codec, AudioCodec::Flac) {
+                    add_flac_compression(ui, &mut self.flac_compression);
+                }
             });
 
             ui.add(egui::Separator::default().vertical());
@@ -198,9 +760,19 @@ impl Compressor {
             ui.vertical(|ui| {
                 ui.label("Video quality: ");
 
+                let enabled = matches!(self.selected_mode, AppMode::Video) && !self.use_output_file_size;
+                ui.add_enabled_ui(enabled, |ui| {
+                    let rate_mode = &mut self.video_rate_mode;
+                    ComboBox::from_id_source("video_rate_mode").selected_text(rate_mode.label()).show_ui(ui, |ui| {
+                        for mode in [ VideoRateMode::QualityFirst, VideoRateMode::BitrateFirst ] {
+                            ui.selectable_value(rate_mode, mode, mode.label());
+                        }
+                    });
+                });
+
                 for (i, text) in QUALITY_GUI_LABELS.iter().enumerate() {
                     let resp = ui.add_enabled(
-                        !self.is_working && matches!(self.selected_mode, AppMode::Video) && !self.use_output_file_size,
+                        enabled,
                         RadioButton::new(i == self.video_quality as usize, *text)
                     );
 
@@ -214,7 +786,7 @@ impl Compressor {
 
             ui.vertical(|ui| {
                 let resolution_btn = ui.add_enabled(
-                    !self.is_working && matches!(self.selected_mode, AppMode::Video),
+                    matches!(self.selected_mode, AppMode::Video),
                     RadioButton::new(self.use_custom_resolution, "Resolution:")
                 );
 
@@ -222,27 +794,58 @@ impl Compressor {
                     self.use_custom_resolution = !self.use_custom_resolution;
                 }
 
+                let enabled = matches!(self.selected_mode, AppMode::Video) && self.use_custom_resolution;
                 for (i, text) in RESOLUTION_GUI_LABELS.iter().enumerate() {
                     let resp = ui.add_enabled(
-                        !self.is_working && matches!(self.selected_mode, AppMode::Video) && self.use_custom_resolution,
-                        RadioButton::new(i == self.selected_resolution, *text)
-                    );
+                        enabled,
+                        RadioButton::new(!self.custom_size && i == self.selected_resolution, *text)
+                    ).on_hover_text("Height of the video, or the width when the video is vertical");
 
                     if resp.clicked() {
                         self.selected_resolution = i;
+                        self.custom_size = false;
                     }
                 }
 
+                if ui.add_enabled(enabled, RadioButton::new(self.custom_size, "Custom")).clicked() {
+                    self.custom_size = true;
+                }
+
+                ui.add_enabled_ui(enabled && self.custom_size, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(DragValue::new(&mut self.custom_width).clamp_range(16..=8192));
+                        ui.label("x");
+                        ui.add(DragValue::new(&mut self.custom_height).clamp_range(16..=8192));
+                    });
+                    add_codec_selector(ui, "scale_fit", &mut self.scale_fit, SCALE_FITS, ScaleFit::label);
+                });
+
+                ui.add_enabled_ui(enabled, |ui| {
+                    ui.checkbox(&mut self.allow_upscale, "Allow upscaling");
+                });
+
+                if enabled {
+                    if let Err(message) = self.selected_scaling().validate() {
+                        ui.colored_label(ui.visuals().warn_fg_color, message);
+                    }
+                }
             });
 
             ui.add(egui::Separator::default().vertical());
 
             ui.vertical(|ui| {
+                ui.add_enabled_ui(matches!(self.selected_mode, AppMode::Video), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label("Codec:");
+                        add_codec_selector(ui, "video_codec", &mut self.video_codec, VIDEO_CODECS, VideoCodec::label);
+                    });
+                });
+
                 ui.label("Compression:");
 
                 for (i, text) in PRESET_GUI_LABELS.iter().enumerate() {
                     let resp = ui.add_enabled(
-                        !self.is_working && matches!(self.selected_mode, AppMode::Video),
+                        matches!(self.selected_mode, AppMode::Video),
                         RadioButton::new(i == self.selected_preset, *text)
                     );
 
@@ -252,21 +855,29 @@ impl Compressor {
                 }
             });
         });
+
+        let shows_bitrates = matches!(self.selected_mode, AppMode::Audio | AppMode::Video) && !self.use_output_file_size;
+        if shows_bitrates && !self.is_batch_input() {
+            self.update_input_media_info(ui.ctx());
+            if let Some(bitrates) = self.quality_bitrates_label() {
+                ui.label(bitrates);
+            }
+        }
     }
 
     fn add_input_selectors(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
             let inputline_string = if matches!(self.selected_mode, AppMode::Download) {
                 "Link to a media you wish to download:"
+            } else if self.dual_mode {
+                "Link to a media you wish to download and convert:"
             } else {
-                "Path to a file you wish to convert:"
+                "Path to a file or a directory you wish to convert:"
             };
             ui.label(inputline_string);
 
-            let pick_file_btn = ui.add_enabled(
-                !self.is_working && !matches!(self.selected_mode, AppMode::Download),
-                Button::new("File selection")
-            );
+            let selection_enabled = !matches!(self.selected_mode, AppMode::Download) && !self.dual_mode;
+            let pick_file_btn = ui.add_enabled(selection_enabled, Button::new("File selection"));
 
             if pick_file_btn.clicked() {
                 let res = rfd::FileDialog::new()
@@ -277,17 +888,28 @@ impl Compressor {
                     self.save_cache();
                 }
             }
+
+            let pick_folder_btn = ui.add_enabled(selection_enabled, Button::new("Directory selection"));
+
+            if pick_folder_btn.clicked() {
+                let res = rfd::FileDialog::new()
+                    .pick_folder();
+
+                if let Some(path) = res {
+                    self.input_path = path.to_str().unwrap().to_string();
+                    self.save_cache();
+                }
+            }
         });
 
 
         ui.horizontal(|ui| {
-            if ui.add_enabled(!self.is_working, Button::new("🗑")).clicked() {
+            if ui.button("🗑").clicked() {
                 self.input_path.clear();
                 self.save_cache();
             }
 
-            let input_path_field = ui.add_enabled(
-                !self.is_working,
+            let input_path_field = ui.add(
                 TextEdit::singleline(&mut self.input_path).desired_width(f32::INFINITY)
             );
 
@@ -296,22 +918,55 @@ impl Compressor {
             }
         });
 
+        if self.is_probing && self.probed_input_path == self.input_path {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Probing the input file...");
+            });
+        }
+
+        let is_batch_input = self.is_batch_input();
+        if is_batch_input {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.batch_recursive, "Include subdirectories");
+                ui.add(egui::Separator::default().vertical());
+                ui.label("Extensions:");
+                ui.add(
+                    TextEdit::singleline(&mut self.batch_extensions).desired_width(f32::INFINITY)
+                ).on_hover_text("Only the files with these extensions are converted, separated with commas");
+            });
+        }
+
         ui.horizontal(|ui| {
-            ui.label("Output file (can be empty):");
+            if is_batch_input {
+                ui.label("Output directory (can be empty):");
 
-            if ui.add_enabled(!self.is_working, Button::new("File saving")).clicked() {
-                let res = rfd::FileDialog::new()
-                    .save_file();
+                if ui.button("Directory selection").clicked() {
+                    let res = rfd::FileDialog::new()
+                        .pick_folder();
 
-                if let Some(path) = res {
-                    self.output_path = path.to_str().unwrap().to_string();
-                    self.save_cache();
+                    if let Some(path) = res {
+                        self.output_path = path.to_str().unwrap().to_string();
+                        self.save_cache();
+                    }
+                }
+            } else {
+                ui.label("Output file (can be empty):");
+
+                if ui.button("File saving").clicked() {
+                    let res = rfd::FileDialog::new()
+                        .save_file();
+
+                    if let Some(path) = res {
+                        self.output_path = path.to_str().unwrap().to_string();
+                        self.save_cache();
+                    }
                 }
             }
         });
 
         ui.horizontal(|ui| {
-            if ui.add_enabled(!self.is_working, Button::new("🗑")).clicked() {
+            if ui.button("🗑").clicked() {
                 self.output_path.clear();
                 self.save_cache();
             }
@@ -326,76 +981,35 @@ impl Compressor {
                 .desired_width(f32::INFINITY).hint_text(output_hinting);
 
             // let output_textbox = TextEdit::singleline(&mut self.output_path).desired_width(f32::INFINITY);
-            let output_path_field = ui.add_enabled(!self.is_working, output_textbox);
+            let output_path_field = ui.add(output_textbox);
             if output_path_field.changed() {
                 self.last_state_update = Some(Instant::now());
             }
         });
     }
 
-    fn create_new_channels(&mut self) -> ThreadChannels {
-        let (log_tx, log_rx) = std::sync::mpsc::channel();
-        let (finished_tx, finished_rx) = std::sync::mpsc::channel();
-        let (abort_tx, abort_rx)       = std::sync::mpsc::channel();
-        let (progress_tx, progress_rx) = std::sync::mpsc::channel();
-
-        self.message_log_rx   = Some(log_rx);
-        self.work_finished_rx = Some(finished_rx);
-        self.work_abort_tx    = Some(abort_tx);
-        self.work_progress_rx = Some(progress_rx);
-
-        let channels = ThreadChannels::new(log_tx, finished_tx, abort_rx, progress_tx);
-        return channels;
-    }
-
     fn add_bottom_buttons(&mut self, ui: &mut egui::Ui) {
         ui.horizontal(|ui| {
-            let action_btn = if self.is_working {
-                "Cancel"
-            } else if matches!(self.selected_mode, AppMode::Download) {
+            let action_btn = if matches!(self.selected_mode, AppMode::Download) {
                 "Download"
+            } else if self.dual_mode {
+                "Download and Convert"
             } else {
                 "Convert"
             };
 
             if ui.button(action_btn).clicked() {
-                if self.is_working {
-                    if let Some(tx) = &self.work_abort_tx {
-                        let _ = tx.send(());
-                    }
-                    return;
-                } 
-
-                if self.input_path.trim().is_empty() {
-                    if matches!(self.selected_mode, AppMode::Download) {
-                        self.popup.error("Input cannot be empty. You must provide a download link.");
-                    } else {
-                        self.popup.error("Input cannot be empty. You must provide a path to a file.");
-                    }
-                    return;
+                if let Err(message) = self.enqueue_job() {
+                    self.popup.error(message);
                 }
+            }
 
-                if !matches!(self.selected_mode, AppMode::Download) && !Path::new(&self.input_path).exists() {
-                    self.popup.error("Input path is incorrect, file does not exist.");
-                } else {
-                    self.is_working = true;
-                    self.progress = 0.0;
-
-                    let channels = self.create_new_channels();
-                    match self.selected_mode {
-                        AppMode::Audio    => self.compress_audio(channels),
-                        AppMode::Video    => self.compress_video(channels),
-                        AppMode::Download => self.download_resource(channels),
-                        AppMode::Advanced => self.compress_advanced(channels),
-                    }
-                }
+            if ui.add_enabled(self.is_working, Button::new("Cancel")).clicked() {
+                self.cancel_running_job();
             }
 
             if ui.button("Exit").clicked() {
-                if let Some(tx) = &self.work_abort_tx {
-                    let _ = tx.send(());
-                }
-
+                self.cancel_running_job();
                 self.should_exit = true;
             }
 
@@ -417,29 +1031,107 @@ impl Compressor {
         });
     }
 
-    fn receive_log_messages(&mut self) {
-        let Some(log_rx) = &self.message_log_rx else {
-            return;
-        };
+    fn add_job_queue(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label(format!("Job queue ({} jobs):", self.job_queue.len()));
 
-        let Ok(log) = log_rx.try_recv() else {
-            return;
-        };
+            if ui.button("Clear finished").clicked() {
+                self.clear_finished_jobs();
+            }
+        });
 
-        let toast = Toast {
-            text: log.text.into(),
-            kind: log.kind,
-            options: ToastOptions::default(),
-        };
+        let mut removed = None;
+        let mut moved_up = None;
+        let mut moved_down = None;
+
+        ScrollArea::vertical().max_height(150.0).show(ui, |ui| {
+            Grid::new("job_queue").striped(true).num_columns(6).show(ui, |ui| {
+                ui.label("");
+                ui.label("Position");
+                ui.label("Job name");
+                ui.label("The input");
+                ui.label("The output");
+                ui.label("Progress / Status");
+                ui.end_row();
+
+                let job_count = self.job_queue.len();
+                for (i, entry) in self.job_queue.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("🗑").on_hover_text("Remove from the queue").clicked() {
+                            removed = Some(i);
+                        }
+                        if ui.add_enabled(i > 0, Button::new("⏶").small()).clicked() {
+                            moved_up = Some(i);
+                        }
+                        if ui.add_enabled(i + 1 < job_count, Button::new("⏷").small()).clicked() {
+                            moved_down = Some(i);
+                        }
+                    });
+
+                    ui.label(format!("{}.", i + 1));
+                    ui.label(entry.job.name());
+                    ui.label(entry.job.input());
+
+                    let output = entry.job.output();
+                    match &entry.job {
+                        CompressorJob::Dual(job) if output.is_empty() => {
+                            ui.label(format!("{} (named after the download)", job.output_dir.display()))
+                        }
+                        _ => ui.label(if output.is_empty() { "-" } else { output }),
+                    };
+
+                    if entry.status == JobStatus::Running {
+                        let progress_bar = egui::ProgressBar::new(entry.progress)
+                            .desired_width(120.0)
+                            .show_percentage();
+                        ui.add(progress_bar);
+                    } else {
+                        ui.label(entry.status.label());
+                    }
+                    ui.end_row();
+                }
+            });
+        });
 
-        self.popup.add(toast);
+        if let Some(i) = removed {
+            self.remove_job(i);
+        } else if let Some(i) = moved_up {
+            self.move_job_up(i);
+        } else if let Some(i) = moved_down {
+            self.move_job_down(i);
+        }
+    }
+}
+
+/// Combo box selecting one of the `codecs`, returns true when the selection changed.
+fn add_codec_selector<T: Copy + PartialEq>(
+    ui: &mut egui::Ui, id: &str, codec: &mut T, codecs: &[T], label: fn(&T) -> &'static str,
+) -> bool {
+    let previous = *codec;
+    ComboBox::from_id_source(id).selected_text(label(codec)).show_ui(ui, |ui| {
+        for option in codecs {
+            ui.selectable_value(codec, *option, label(option));
+        }
+    });
+    *codec != previous
+}
+
+fn add_flac_compression(ui: &mut egui::Ui, compression: &mut u8) {
+    ui.horizontal(|ui| {
+        ui.label("Compression level:");
+        ui.add(Slider::new(compression, 0..=12));
+    });
+}
 
-        // match message {
-        //     MessageLog::Success(s) => self.popup.success(s),
-        //     MessageLog::Basic(s)   => self.popup.basic(s),
-        //     MessageLog::Info(s)    => self.popup.info(s),
-        //     MessageLog::Warning(s) => self.popup.warning(s),
-        //     MessageLog::Error(s)   => self.popup.error(s),
-        // };
+/// Checkbox adding the subtitle track to the selected `tracks` or removing it from them
+fn add_track_checkbox(ui: &mut egui::Ui, tracks: &mut Vec<usize>, track: usize, text: &str) {
+    let mut selected = tracks.contains(&track);
+    if ui.checkbox(&mut selected, text).changed() {
+        if selected {
+            tracks.push(track);
+            tracks.sort();
+        } else {
+            tracks.retain(|selected| *selected != track);
+        }
     }
 }
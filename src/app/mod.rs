@@ -1,55 +1,34 @@
 use std::{
-    sync::mpsc::{Receiver, Sender}, path::PathBuf, fs, time::Instant, io::Write, 
+    path::PathBuf, fs, time::Instant, io::Write, sync::mpsc::{self, Receiver, Sender},
 };
 
 use eframe::{emath::Align2, egui};
-use egui_toast::{Toasts, ToastKind};
-
-use crate::{*, popup::MessageLog};
-
+use egui_toast::Toasts;
+
+use crate::*;
+use crate::engine::*;
+use crate::engine::animation::{AnimationFormat, AnimationOptions, Dither, ANIMATION_FORMATS, DITHERS};
+use crate::engine::codec::{AudioCodec, VideoCodec, AUDIO_CODECS, VIDEO_CODECS, DEFAULT_FLAC_COMPRESSION};
+use crate::engine::crop::Crop;
+use crate::engine::framerate::{describe_frame_rate, FrameRate, FrameRateMethod, FrameRateTarget, FRAME_RATE_METHODS};
+use crate::engine::scale::{ScaleFit, ScaleSize, Scaling, SCALE_FITS};
+use crate::engine::loudness::{Loudnorm, INTEGRATED_RANGE, LOUDNESS_RANGE, TRUE_PEAK_RANGE};
+use crate::engine::subtitles::{is_text_subtitle, track_label, BurnIn, SubtitleTracks, Subtitles};
+use crate::engine::thumbnail::{ImageFormat, ThumbnailKind, ThumbnailOptions, IMAGE_FORMATS, MAX_SHEET_SIZE};
+use crate::engine::trim::{format_time, parse_time, CutMode, Trim};
+
+mod crop_preview;
 mod gui;
-mod processes;
+mod queue;
+mod timeline;
 
 // use gui::*;
 // use processes::*;
 
-const PRESET_FFMPEG_STRINGS:     &[&str] = &[ "veryslow", "slow", "medium", "fast", "ultrafast", ];
-const RESOLUTION_FFMPEG_STRINGS: &[&str] = &[ "1080", "720", "480", "360", "144", ];
 const PRESET_GUI_LABELS:         &[&str] = &[ "Very Precise", "Precise", "Balanced", "Fast", "Very Fast", ];
 const RESOLUTION_GUI_LABELS:     &[&str] = &[ "1080p", "720p", "480p", "360p", "144p", ];
 const QUALITY_GUI_LABELS:        &[&str] = &[ "Original", "Good", "Medium", "Bad", "Poop" ];
 
-// Qulity could be a fraction of the original bitrate 
-#[derive(Default, Copy, Clone)]
-enum Quality {
-    #[default]
-    // 100%
-    Original = 0,
-    // 90%
-    Good     = 1,
-    // 60%
-    Medium   = 2,
-    // 30%
-    Bad      = 3,
-    // 10%
-    Poop     = 4,
-}
-
-#[allow(dead_code)]
-#[derive(Default)]
-enum AppMode {
-    #[default]
-    Audio    = 0,
-    Video    = 1,
-    // Audio and Video quality selection option for download (or maybe don't to this and just reencode?)
-    Download = 2,
-    // When advanced is selected you can still select either audio or video but the selections the
-    // quality options are greatly expanded (audio bitrade/sample rate, video bitrate (actual kB/s values in the selections))
-    Advanced = 3,
-    // Download mode is always selected, but you can also select either audio or video option
-    // DualMode = 4,
-}
-
 #[allow(dead_code)]
 enum OldMessageLog {
     Basic(&'static str),
@@ -59,75 +38,26 @@ enum OldMessageLog {
     Error(&'static str),
 }
 
-struct ThreadChannels {
-    message_log_tx: Sender<MessageLog>,
-    work_finished_tx: Sender<()>,
-    work_abort_rx: Receiver<()>,
-    work_progress_tx: Sender<f32>,
-}
-
-#[allow(dead_code)]
-struct ConvertOptions {
-    audio_enabled: bool,
-    audio_bitrate: u32,
-    audio_sample_rate: u32,
-
-    video_enabled: bool,
-    video_bitrate: u32,
-    video_width: u32,
-    video_height: u32,
-
-    preset: &'static str,
-    tune: &'static str,
-    crf: u8,
-}
-
-
-impl Quality {
-    fn from_usize(num: usize) -> Self {
-        match num {
-            0 => Quality::Original,
-            1 => Quality::Good,
-            2 => Quality::Medium,
-            3 => Quality::Bad,
-            4 => Quality::Poop,
-            _ => panic!("Incorrect size"),
-        }
-    }
-}
-
-//
-// TODO: Create job queue, allow user (me) to queue multiple convertion jobs (that would also mean
-//       that you could queue multiple files at once, the ui should also display the queue)
 //
-//       Job Queue: (an example of running download and two-pass convertion job)
+//       Job Queue: (an example of running download and convertion job)
 //       +-------+----------+----------+------------------+---------------+-------------------+
 //       | Btns  | Position | Job name | The input        | The Output    | Progress / Status |
 //       +-------+----------+----------+------------------+---------------+-------------------+
 //       | T ^ v |    1.    | Download | https://link.com | something.mp4 | [###-------] 30 % |
-//       | T ^ v |    2.    | Analize  | something.mp4    |      -        | [----------]  0 % |
-//       | T ^ v |    3.    | Convert  | something.mp4    | output.mp4    | [----------]  0 % |
+//       | T ^ v |    2.    | Convert  | something.mp4    | output.mp4    | [----------]  0 % |
 //       +-------+----------+----------+------------------+---------------+-------------------+
 //
 //       - The T icon is going to be the "Trash" emote - remove from queue button
 //       - The "^" emote is move up and "v" emote is move down
 //
 
-struct DownloadJob {
-    input_link: String,
-    output_file: String
-}
-
-struct ConvertionJob {
-    input_file: String,
-    output_file: String
-    // Other options
-}
-
-enum CompressorJob {
-    Convert(Box<ConvertionJob>),
-    Download(Box<DownloadJob>),
-    Analyze,
+struct QueuedJob {
+    job: CompressorJob,
+    status: JobStatus,
+    /// Value between 0.0 and 1.0
+    progress: f32,
+    /// Jobs created from the same input directory share the batch id
+    batch: Option<usize>,
 }
 
 /// Represents the state of the GUI and holds the data of the program
@@ -136,9 +66,15 @@ pub struct Compressor {
     // State of the program
     //
 
+    /// Set when a job from the queue is currently being processed
     is_working: bool,
     should_exit: bool,
-    progress: f32,
+    progress: WorkProgress,
+
+    /// Jobs waiting to be processed, the one being processed and the ones already done
+    job_queue: Vec<QueuedJob>,
+    /// Id given to the next batch of jobs created from a directory
+    next_batch_id: usize,
 
     /// Timer storing the last gui state update time. Set to when cache is up to date.
     last_state_update: Option<Instant>,
@@ -147,14 +83,8 @@ pub struct Compressor {
     // Communication between threads
     //
     
-    /// Receives a message from the working thread that gets displayed by the popup
-    message_log_rx: Option<Receiver<MessageLog>>,
-    /// Receives an information from the working thread that the work has finished
-    work_finished_rx: Option<Receiver<()>>,
-    /// Sends an information to work threads to abort its execution
-    work_abort_tx: Option<Sender<()>>,
-    // Sends current progress status to the GUI thread (value between 0.0 and 1.0)
-    work_progress_rx: Option<Receiver<f32>>,
+    /// Receives the progress and the result of the job being processed, sends the cancel request
+    running_job: Option<JobHandle>,
 
     //
     // Options selected in the main gui thread
@@ -169,21 +99,100 @@ pub struct Compressor {
     // TODO: Make this an optional - user can disable the audio if in AppMode::Video or Advanced
     audio_quality: Quality,
     video_quality: Quality,
+    /// Whether the video quality levels select the constant quality or the bitrate
+    video_rate_mode: VideoRateMode,
 
     dual_mode: bool,
     // advanced_mode: bool,
 
     /// Output resolution of the video
     use_custom_resolution: bool,
+    /// Index into RESOLUTIONS, the short side of the video
     selected_resolution: usize,
+    /// Scale into the custom width and height instead of the selected resolution
+    custom_size: bool,
+    custom_width: u32,
+    custom_height: u32,
+    scale_fit: ScaleFit,
+    allow_upscale: bool,
+
+    /// Crop the black bars off the video
+    crop_enabled: bool,
+    /// Black bars detected in the input file
+    crop_preview: crop_preview::CropPreview,
+
+    /// Frame rate of the Video mode
+    frame_rate: FrameRate,
+    /// Value of the frame rate target, kept when switching between capping and converting
+    frame_rate_value: f64,
 
     /// The -preset flag used by ffmpeg (default preset is always medium).
     selected_preset: usize,
+
+    /// Video codec used in the Video and Advanced modes
+    video_codec: VideoCodec,
+    /// Audio codec used in the Audio mode and the Advanced mode without the video
+    audio_codec: AudioCodec,
+    /// Compression level used with the FLAC codec
+    flac_compression: u8,
     
     /// Approximate output file size in MB
     use_output_file_size: bool,
     output_file_size: String,
+    /// Video to audio bitrate ratio used in the target-size mode
     bitrate_ratio: f32,
+    /// Encode again when the output turns out larger than the selected size
+    verify_output_size: bool,
+
+    /// Information about the input file, used to show the bitrates of the target-size mode
+    input_media_info: Option<MediaInfo>,
+    /// The input path the input_media_info was probed for
+    probed_input_path: String,
+    /// Set while ffprobe is reading the probed_input_path
+    is_probing: bool,
+    /// Media info probed in the background, tagged with the path it was probed for
+    probe_tx: Sender<(String, Option<MediaInfo>)>,
+    probe_rx: Receiver<(String, Option<MediaInfo>)>,
+
+    /// Keep only a part of the media
+    trim_enabled: bool,
+    trim_start: String,
+    /// The end of the kept part, or its duration when trim_by_duration is set
+    trim_end: String,
+    trim_by_duration: bool,
+    cut_mode: CutMode,
+    /// Thumbnails of the input with the handles of the trimmed part
+    timeline: timeline::Timeline,
+
+    /// Normalize the loudness of the audio to the selected targets
+    loudnorm_enabled: bool,
+    loudnorm: Loudnorm,
+
+    /// Subtitle tracks (stream indexes) of the input kept in the output
+    keep_subtitles: Vec<usize>,
+    /// Subtitle tracks (stream indexes) of the input written into separate files
+    extract_subtitles: Vec<usize>,
+    burn_subtitles: BurnIn,
+
+    /// Options selected in the Advanced mode
+    advanced_options: ConvertOptions,
+
+    /// Options of the Thumbnail mode, the kind of the thumbnail is built out of the fields below
+    thumbnail_options: ThumbnailOptions,
+    contact_sheet: bool,
+    /// Time of the single frame, empty for the middle of the video
+    thumbnail_time: String,
+    sheet_columns: u32,
+    sheet_rows: u32,
+    sheet_timestamps: bool,
+
+    /// Options of the Animation mode
+    animation_options: AnimationOptions,
+
+    /// Extensions of the files converted when the input is a directory, separated with commas
+    batch_extensions: String,
+    /// Also convert the files in the subdirectories of the input directory
+    batch_recursive: bool,
 
     /// The popups that show up in the top right corner
     popup: Toasts,
@@ -273,75 +282,18 @@ impl Compressor {
     }
 }
 
-#[allow(dead_code)]
-impl ThreadChannels {
-    fn new(log: Sender<MessageLog>, finished: Sender<()>, abort: Receiver<()>, progress: Sender<f32>) -> Self {
-        Self {
-            message_log_tx: log,
-            work_finished_tx: finished,
-            work_abort_rx: abort,
-            work_progress_tx: progress,
-        }
-    }
-
-    fn send_error(&self, message: &'static str) {
-        let log = MessageLog {
-            text: message,
-            kind: ToastKind::Error,
-        };
-        let _ = self.message_log_tx.send(log);
-        let _ = self.work_finished_tx.send(());
-    }
-
-    fn send_info(&self, message: &'static str) {
-        let log = MessageLog {
-            text: message,
-            kind: ToastKind::Info,
-        };
-        let _ = self.message_log_tx.send(log);
-        let _ = self.work_finished_tx.send(());
-    }
-
-    fn send_success(&self, message: &'static str) {
-        let log = MessageLog {
-            text: message,
-            kind: ToastKind::Success,
-        };
-        let _ = self.message_log_tx.send(log);
-        let _ = self.work_finished_tx.send(());
-    }
-
-    fn send_warning(&self, message: &'static str) {
-        let log = MessageLog {
-            text: message,
-            kind: ToastKind::Warning,
-        };
-        let _ = self.message_log_tx.send(log);
-        let _ = self.work_finished_tx.send(());
-    }
-
-    fn send_custom(&self, message: &'static str, custom: u32) {
-        let log = MessageLog {
-            text: message,
-            kind: ToastKind::Custom(custom),
-        };
-        let _ = self.message_log_tx.send(log);
-        let _ = self.work_finished_tx.send(());
-    }
-}
-
 impl Default for Compressor {
     fn default() -> Self {
+        let (probe_tx, probe_rx) = mpsc::channel();
         Self {
             is_working: false,
             should_exit: false,
-            progress: 1.0,
+            progress: WorkProgress::default(),
+            job_queue: Vec::new(),
+            next_batch_id: 0,
             last_state_update: None,
 
-            message_log_rx: None,
-            work_finished_rx: None,
-            work_abort_tx: None,
-            work_progress_rx: None,
+            running_job: None,
 
             input_path: String::new(),
             output_path: String::new(),
@@ -349,18 +301,63 @@ impl Default for Compressor {
             selected_mode: AppMode::default(),
             audio_quality: Quality::default(),
             video_quality: Quality::default(),
+            video_rate_mode: VideoRateMode::default(),
 
             dual_mode: false,
             // advanced_mode: false,
 
             use_custom_resolution: false,
             selected_resolution: 0,
+            custom_size: false,
+            custom_width: 1280,
+            custom_height: 720,
+            scale_fit: ScaleFit::default(),
+            allow_upscale: false,
+            crop_enabled: false,
+            crop_preview: crop_preview::CropPreview::default(),
+            frame_rate: FrameRate::default(),
+            frame_rate_value: 30.0,
 
             selected_preset: 2,
+            video_codec: VideoCodec::default(),
+            audio_codec: AudioCodec::default(),
+            flac_compression: DEFAULT_FLAC_COMPRESSION,
 
             use_output_file_size: false,
             output_file_size: String::from("0"),
             bitrate_ratio: 10.0,
+            verify_output_size: true,
+
+            input_media_info: None,
+            probed_input_path: String::new(),
+            is_probing: false,
+            probe_tx,
+            probe_rx,
+
+            trim_enabled: false,
+            trim_start: String::new(),
+            trim_end: String::new(),
+            trim_by_duration: false,
+            cut_mode: CutMode::default(),
+            timeline: timeline::Timeline::default(),
+            loudnorm_enabled: false,
+            loudnorm: Loudnorm::default(),
+            keep_subtitles: Vec::new(),
+            extract_subtitles: Vec::new(),
+            burn_subtitles: BurnIn::None,
+            advanced_options: ConvertOptions::default(),
+
+            thumbnail_options: ThumbnailOptions::default(),
+            contact_sheet: false,
+            thumbnail_time: String::new(),
+            sheet_columns: 4,
+            sheet_rows: 3,
+            sheet_timestamps: true,
+
+            animation_options: AnimationOptions::default(),
+
+            batch_extensions: String::from(batch::DEFAULT_BATCH_EXTENSIONS),
+            batch_recursive: false,
         
             popup: Toasts::new()
                 .anchor(Align2::RIGHT_TOP, (-10.0, 10.0))
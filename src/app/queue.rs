@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+
+use super::*;
+use crate::engine::batch::batch_jobs;
+use crate::popup::message_toast;
+
+impl Compressor {
+    pub(super) fn output_extension(&self) -> Option<String> {
+        // Nothing is converted in the Download mode
+        if matches!(self.selected_mode, AppMode::Download) {
+            return None;
+        }
+
+        let job = self.create_convertion_job(self.input_path.clone()).ok()?;
+        Some(job.output_extension())
+    }
+
+    /// Set when the input is a directory and every matching file in it gets converted
+    pub(super) fn is_batch_input(&self) -> bool {
+        !matches!(self.selected_mode, AppMode::Download) && !self.dual_mode && Path::new(self.input_path.trim()).is_dir()
+    }
+
+    /// Builds a job out of the currently selected options and puts it at the end of the queue.
+    pub(super) fn enqueue_job(&mut self) -> Result<(), &'static str> {
+        if self.input_path.trim().is_empty() {
+            if matches!(self.selected_mode, AppMode::Download) || self.dual_mode {
+                return Err("Input cannot be empty. You must provide a download link.");
+            } else {
+                return Err("Input cannot be empty. You must provide a path to a file.");
+            }
+        }
+
+        let job = if matches!(self.selected_mode, AppMode::Download) {
+            let job = DownloadJob {
+                input_link: self.input_path.trim().to_string(),
+                output_file: self.output_path.trim().to_string(),
+            };
+            CompressorJob::Download(Box::new(job))
+        } else if self.dual_mode {
+            let mut convertion = self.create_convertion_job(String::new())?;
+
+            // Without the output path it is created once the name of the downloaded file is known
+            if !self.output_path.trim().is_empty() {
+                let extension = convertion.output_extension();
+                convertion.output_file = output_path_for("", &self.output_path, &extension).unwrap_or_default();
+            }
+
+            let Some(output_dir) = default_download_dir() else {
+                return Err("Failed to find the download directory. Please select the output path.");
+            };
+
+            let job = DualJob {
+                input_link: self.input_path.trim().to_string(),
+                convertion,
+                output_dir,
+            };
+            CompressorJob::Dual(Box::new(job))
+        } else if self.is_batch_input() {
+            return self.enqueue_batch();
+        } else {
+            if !Path::new(&self.input_path).exists() {
+                return Err("Input path is incorrect, file does not exist.");
+            }
+
+            let mut job = self.create_convertion_job(self.input_path.clone())?;
+
+            let Some(output_file) = output_path_for(&self.input_path, &self.output_path, &job.output_extension()) else {
+                return Err("Failed to construct output path for selected input.");
+            };
+            job.output_file = output_file;
+
+            CompressorJob::Convert(Box::new(job))
+        };
+
+        self.job_queue.push(QueuedJob {
+            job,
+            status: JobStatus::Queued,
+            progress: 0.0,
+            batch: None,
+        });
+
+        Ok(())
+    }
+
+    /// Puts a convertion job for every matching file of the input directory at the end of the queue.
+    fn enqueue_batch(&mut self) -> Result<(), &'static str> {
+        let template = self.create_convertion_job(String::new())?;
+        let input_dir = PathBuf::from(self.input_path.trim());
+        let jobs = batch_jobs(&template, &input_dir, &self.output_path, &self.batch_extensions, self.batch_recursive)?;
+
+        let batch = self.next_batch_id;
+        self.next_batch_id += 1;
+
+        for job in jobs {
+            self.job_queue.push(QueuedJob {
+                job: CompressorJob::Convert(Box::new(job)),
+                status: JobStatus::Queued,
+                progress: 0.0,
+                batch: Some(batch),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Number of the finished jobs, the number of all jobs and the overall progress of the batch
+    pub(super) fn batch_progress(&self, batch: usize) -> (usize, usize, f32) {
+        let jobs = self.job_queue.iter().filter(|entry| entry.batch == Some(batch));
+
+        let mut done = 0;
+        let mut total = 0;
+        let mut progress = 0.0;
+        for entry in jobs {
+            total += 1;
+            match entry.status {
+                JobStatus::Queued  => {}
+                JobStatus::Running => progress += entry.progress,
+                _ => {
+                    done += 1;
+                    progress += 1.0;
+                }
+            }
+        }
+
+        let fraction = if total > 0 { progress / total as f32 } else { 0.0 };
+        (done, total, fraction)
+    }
+
+    /// Creates the convertion job out of the selected options, with the output file left empty.
+    pub(super) fn create_convertion_job(&self, input_file: String) -> Result<ConvertionJob, &'static str> {
+        let output_file_size = if self.use_output_file_size && self.selected_mode.uses_target_size() {
+            let Ok(size) = self.output_file_size.trim().parse::<f32>() else {
+                return Err("Failed to read the output file size.");
+            };
+            Some(size)
+        } else {
+            None
+        };
+
+        if matches!(self.selected_mode, AppMode::Advanced) {
+            self.advanced_options.validate(self.video_codec, self.audio_codec)?;
+        }
+
+        let trim = if self.trim_enabled { self.selected_trim()? } else { Trim::default() };
+
+        let loudnorm = self.loudnorm_enabled && matches!(self.selected_mode, AppMode::Audio | AppMode::Video);
+        if loudnorm {
+            self.loudnorm.validate()?;
+        }
+
+        let subtitles = if !matches!(self.selected_mode, AppMode::Video) {
+            Subtitles::default()
+        } else if self.is_batch_input() || self.dual_mode {
+            // Tracks are selected out of the probed input, so the other files can only get the subtitle file
+            let burn = match &self.burn_subtitles {
+                BurnIn::File(path) => BurnIn::File(path.clone()),
+                _ => BurnIn::None,
+            };
+            Subtitles { burn, ..Default::default() }
+        } else {
+            Subtitles {
+                keep: SubtitleTracks::Selected(self.keep_subtitles.clone()),
+                burn: self.burn_subtitles.clone(),
+                extract: SubtitleTracks::Selected(self.extract_subtitles.clone()),
+            }
+        };
+
+        let thumbnail_options = if matches!(self.selected_mode, AppMode::Thumbnail) {
+            self.selected_thumbnail()?
+        } else {
+            ThumbnailOptions::default()
+        };
+
+        if matches!(self.selected_mode, AppMode::Animation) {
+            self.animation_options.validate()?;
+        }
+
+        let frame_rate = if matches!(self.selected_mode, AppMode::Video) { self.frame_rate } else { FrameRate::default() };
+        frame_rate.validate()?;
+
+        let crop = if matches!(self.selected_mode, AppMode::Video) { self.selected_crop() } else { Crop::None };
+
+        let scaling = self.use_custom_resolution.then(|| self.selected_scaling());
+        if let Some(scaling) = scaling.filter(|_| matches!(self.selected_mode, AppMode::Video)) {
+            scaling.validate()?;
+        }
+
+        Ok(ConvertionJob {
+            input_file,
+            output_file: String::new(),
+            mode: self.selected_mode,
+            audio_quality: self.audio_quality,
+            video_quality: self.video_quality,
+            video_rate_mode: self.video_rate_mode,
+            scaling,
+            crop,
+            frame_rate,
+            preset: self.selected_preset,
+            video_codec: self.video_codec,
+            audio_codec: self.audio_codec,
+            flac_compression: self.flac_compression,
+            output_file_size,
+            bitrate_ratio: self.bitrate_ratio,
+            verify_output_size: self.verify_output_size,
+            trim,
+            loudnorm: loudnorm.then_some(self.loudnorm),
+            subtitles,
+            advanced_options: self.advanced_options.clone(),
+            thumbnail_options,
+            animation_options: self.animation_options.clone(),
+        })
+    }
+
+    /// Scaling selected in the resolution settings of the Video mode
+    pub(super) fn selected_scaling(&self) -> Scaling {
+        let size = if self.custom_size {
+            ScaleSize::Box { width: self.custom_width, height: self.custom_height }
+        } else {
+            ScaleSize::ShortSide(RESOLUTIONS[self.selected_resolution])
+        };
+        Scaling { size, fit: self.scale_fit, upscale: self.allow_upscale }
+    }
+
+    /// Crop of the black bars. The crop detected in the GUI is used for its input, any other input gets
+    /// the bars detected right before its convertion.
+    pub(super) fn selected_crop(&self) -> Crop {
+        if !self.crop_enabled {
+            return Crop::None;
+        }
+
+        match self.crop_preview.detected(&self.input_path) {
+            Some(rect) if !self.is_batch_input() && !self.dual_mode => Crop::Rect(rect),
+            _ => Crop::Detect,
+        }
+    }
+
+    /// Thumbnail selected in the Thumbnail mode
+    pub(super) fn selected_thumbnail(&self) -> Result<ThumbnailOptions, &'static str> {
+        let kind = if self.contact_sheet {
+            ThumbnailKind::ContactSheet { columns: self.sheet_columns, rows: self.sheet_rows, timestamps: self.sheet_timestamps }
+        } else if self.thumbnail_time.trim().is_empty() {
+            ThumbnailKind::Frame(None)
+        } else {
+            let Some(time) = parse_time(&self.thumbnail_time) else {
+                return Err("Failed to read the time of the frame.");
+            };
+            ThumbnailKind::Frame(Some(time))
+        };
+
+        let options = ThumbnailOptions { kind, ..self.thumbnail_options.clone() };
+        options.validate()?;
+        Ok(options)
+    }
+
+    /// Part of the media selected in the trim settings, the second field is either the end or the duration.
+    pub(super) fn selected_trim(&self) -> Result<Trim, &'static str> {
+        let parse = |text: &str, error| match text.trim() {
+            "" => Ok(None),
+            text => parse_time(text).map(Some).ok_or(error),
+        };
+
+        let start = parse(&self.trim_start, "Failed to read the start of the trimmed part.")?;
+        let end = parse(&self.trim_end, "Failed to read the end of the trimmed part.")?;
+        let end = if self.trim_by_duration { end.map(|duration| start.unwrap_or(0) + duration) } else { end };
+
+        let trim = Trim { start, end, cut_mode: self.cut_mode };
+        trim.validate()?;
+        Ok(trim)
+    }
+
+    /// Starts processing the first queued job, unless some other job is still being processed.
+    pub(super) fn start_next_job(&mut self) {
+        if self.is_working || self.should_exit {
+            return;
+        }
+
+        let Some(index) = self.job_queue.iter().position(|entry| entry.status == JobStatus::Queued) else {
+            return;
+        };
+
+        self.is_working = true;
+        self.progress = WorkProgress::default();
+
+        let entry = &mut self.job_queue[index];
+        entry.status = JobStatus::Running;
+        self.running_job = Some(entry.job.start());
+    }
+
+    /// Receives progress and the finish status of the job currently being processed.
+    pub(super) fn poll_running_job(&mut self) {
+        if !self.is_working {
+            return;
+        }
+
+        let Some(handle) = &self.running_job else {
+            return;
+        };
+
+        let mut finished = None;
+        for event in handle.poll() {
+            match event {
+                JobEvent::Progress(new_progress) => self.progress = new_progress,
+                JobEvent::Message(log)           => { self.popup.add(message_toast(log)); }
+                JobEvent::Finished(status)       => finished = Some(status),
+            }
+        }
+
+        let running = self.job_queue.iter_mut().find(|entry| entry.status == JobStatus::Running);
+        if let Some(entry) = running {
+            entry.progress = self.progress.fraction;
+            if let Some(status) = finished {
+                entry.status = status;
+                if status == JobStatus::Finished {
+                    entry.progress = 1.0;
+                }
+            }
+        }
+
+        if finished.is_some() {
+            self.is_working = false;
+            self.running_job = None;
+        }
+    }
+
+    pub(super) fn cancel_running_job(&self) {
+        if let Some(handle) = &self.running_job {
+            handle.cancel();
+        }
+    }
+
+    /// Removes the job from the queue. Removing the running job cancels it.
+    pub(super) fn remove_job(&mut self, index: usize) {
+        if index >= self.job_queue.len() {
+            return;
+        }
+
+        if self.job_queue[index].status == JobStatus::Running {
+            self.cancel_running_job();
+        }
+
+        self.job_queue.remove(index);
+    }
+
+    pub(super) fn move_job_up(&mut self, index: usize) {
+        if index > 0 && index < self.job_queue.len() {
+            self.job_queue.swap(index, index - 1);
+        }
+    }
+
+    pub(super) fn move_job_down(&mut self, index: usize) {
+        if index + 1 < self.job_queue.len() {
+            self.job_queue.swap(index, index + 1);
+        }
+    }
+
+    /// Removes all of the jobs that are no longer waiting or running.
+    pub(super) fn clear_finished_jobs(&mut self) {
+        self.job_queue.retain(|entry| matches!(entry.status, JobStatus::Queued | JobStatus::Running));
+    }
+}
@@ -0,0 +1,221 @@
+//! Timeline of the input file with thumbnails, used to select the trimmed part with the mouse.
+
+use std::{ops::RangeInclusive, sync::mpsc::{self, Receiver, Sender}};
+
+use eframe::egui::{self, *};
+
+use crate::MediaInfo;
+use crate::engine::frames::{extract_frame, thumbnail_times, Frame};
+use crate::engine::runner::SystemRunner;
+use crate::engine::trim::format_time;
+
+const THUMBNAIL_COUNT:  usize = 12;
+const THUMBNAIL_HEIGHT: usize = 48;
+const PREVIEW_HEIGHT:   usize = 180;
+const HANDLE_WIDTH:     f32   = 6.0;
+
+#[derive(Clone, Copy)]
+enum FrameKind {
+    Thumbnail(usize),
+    Preview,
+}
+
+/// Frame decoded in the background, tagged with the generation of the input it was decoded from
+type DecodedFrame = (u64, FrameKind, Frame);
+
+pub(super) struct Timeline {
+    /// Input the thumbnails are extracted from
+    input_path: String,
+    media_info: Option<MediaInfo>,
+    /// Increased whenever the input changes, so the frames of the previous input are dropped
+    generation: u64,
+
+    thumbnails: Vec<Option<TextureHandle>>,
+    /// Exact frame at the position of the last clicked handle
+    preview: Option<(u64, TextureHandle)>,
+
+    frame_tx: Sender<DecodedFrame>,
+    frame_rx: Receiver<DecodedFrame>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        let (frame_tx, frame_rx) = mpsc::channel();
+        Self {
+            input_path: String::new(),
+            media_info: None,
+            generation: 0,
+            thumbnails: Vec::new(),
+            preview: None,
+            frame_tx,
+            frame_rx,
+        }
+    }
+}
+
+impl Timeline {
+    /// Starts extracting the thumbnails when the input changed.
+    pub(super) fn load(&mut self, ctx: &egui::Context, input_path: &str, media_info: &MediaInfo) {
+        if self.input_path == input_path {
+            return;
+        }
+
+        self.input_path = String::from(input_path);
+        self.media_info = Some(media_info.clone());
+        self.generation += 1;
+        self.preview = None;
+        self.thumbnails.clear();
+
+        // Audio files get the timeline without the thumbnails
+        let Some(duration) = media_info.duration().filter(|_| media_info.first_video().is_some()) else {
+            return;
+        };
+
+        self.thumbnails = vec![None; THUMBNAIL_COUNT];
+        let requests = thumbnail_times(duration, THUMBNAIL_COUNT)
+            .into_iter()
+            .enumerate()
+            .map(|(i, time)| (FrameKind::Thumbnail(i), time))
+            .collect();
+        self.spawn_extraction(ctx, requests, THUMBNAIL_HEIGHT);
+    }
+
+    /// Decodes the frames one after another on a separate thread, the GUI is repainted after each one.
+    fn spawn_extraction(&self, ctx: &egui::Context, requests: Vec<(FrameKind, u64)>, height: usize) {
+        let Some(media_info) = self.media_info.clone() else {
+            return;
+        };
+
+        let ctx = ctx.clone();
+        let frame_tx = self.frame_tx.clone();
+        let input_path = self.input_path.clone();
+        let generation = self.generation;
+
+        std::thread::spawn(move || {
+            for (kind, time) in requests {
+                let Some(frame) = extract_frame(&SystemRunner, &input_path, &media_info, time, height) else {
+                    continue;
+                };
+
+                if frame_tx.send((generation, kind, frame)).is_err() {
+                    return;
+                }
+                ctx.request_repaint();
+            }
+        });
+    }
+
+    fn receive_frames(&mut self, ctx: &egui::Context) {
+        for (generation, kind, frame) in self.frame_rx.try_iter() {
+            if generation != self.generation {
+                continue;
+            }
+
+            let image = ColorImage::from_rgba_unmultiplied([ frame.width, frame.height ], &frame.rgba);
+            match kind {
+                FrameKind::Thumbnail(i) => {
+                    let texture = ctx.load_texture(format!("thumbnail-{i}"), image, TextureOptions::LINEAR);
+                    if let Some(thumbnail) = self.thumbnails.get_mut(i) {
+                        *thumbnail = Some(texture);
+                    }
+                }
+                FrameKind::Preview => {
+                    let texture = ctx.load_texture("trim-preview", image, TextureOptions::LINEAR);
+                    self.preview = Some((frame.time, texture));
+                }
+            }
+        }
+    }
+
+    /// Shows the timeline with the handles of the trimmed part. The `start` and `end` (in ms) are moved by
+    /// dragging the handles, returns true when any of them changed.
+    pub(super) fn show(&mut self, ui: &mut egui::Ui, start: &mut u64, end: &mut u64) -> bool {
+        self.receive_frames(ui.ctx());
+
+        let duration = self.media_info.as_ref().and_then(MediaInfo::duration).unwrap_or(0);
+        if duration == 0 {
+            return false;
+        }
+
+        let size = vec2(ui.available_width(), THUMBNAIL_HEIGHT as f32);
+        let (rect, _) = ui.allocate_exact_size(size, Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 0.0, ui.visuals().extreme_bg_color);
+
+        // Thumbnails are stretched over equal parts of the timeline
+        let slot_width = rect.width() / self.thumbnails.len().max(1) as f32;
+        let uv = Rect::from_min_max(pos2(0.0, 0.0), pos2(1.0, 1.0));
+        for (i, thumbnail) in self.thumbnails.iter().enumerate() {
+            if let Some(texture) = thumbnail {
+                let slot = Rect::from_min_size(pos2(rect.left() + slot_width * i as f32, rect.top()), vec2(slot_width, rect.height()));
+                painter.image(texture.id(), slot, uv, Color32::WHITE);
+            }
+        }
+
+        *end = (*end).min(duration);
+        *start = (*start).min(end.saturating_sub(1));
+
+        // Dropped parts of the media are darkened
+        let shade = Color32::from_black_alpha(160);
+        let start_x = time_to_x(rect, duration, *start);
+        let end_x = time_to_x(rect, duration, *end);
+        painter.rect_filled(Rect::from_min_max(rect.left_top(), pos2(start_x, rect.bottom())), 0.0, shade);
+        painter.rect_filled(Rect::from_min_max(pos2(end_x, rect.top()), rect.right_bottom()), 0.0, shade);
+
+        let start_range = 0..=end.saturating_sub(1);
+        let end_range = *start + 1..=duration;
+        let start_changed = self.add_handle(ui, rect, "trim_start_handle", start, start_range, duration);
+        let end_changed = self.add_handle(ui, rect, "trim_end_handle", end, end_range, duration);
+
+        if let Some((time, texture)) = &self.preview {
+            ui.label(format!("Frame at {}", format_time(*time)));
+            ui.image(texture.id(), texture.size_vec2());
+        }
+
+        start_changed || end_changed
+    }
+
+    /// Draggable handle at the `time`, kept in the `range`. Clicking it (or letting it go after dragging)
+    /// decodes the exact frame at its position.
+    fn add_handle(
+        &mut self, ui: &mut egui::Ui, rect: Rect, id: &str, time: &mut u64, range: RangeInclusive<u64>, duration: u64,
+    ) -> bool {
+        let x = time_to_x(rect, duration, *time);
+        let grab_rect = Rect::from_center_size(pos2(x, rect.center().y), vec2(HANDLE_WIDTH * 3.0, rect.height()));
+        let response = ui.interact(grab_rect, ui.id().with(id), Sense::click_and_drag());
+
+        let mut changed = false;
+        if response.dragged() {
+            if let Some(pointer) = response.interact_pointer_pos() {
+                let new_time = x_to_time(rect, duration, pointer.x).clamp(*range.start(), *range.end());
+                changed = new_time != *time;
+                *time = new_time;
+            }
+        }
+
+        if response.clicked() || response.drag_released() {
+            self.spawn_extraction(ui.ctx(), vec![ (FrameKind::Preview, *time) ], PREVIEW_HEIGHT);
+        }
+
+        let color = if response.hovered() || response.dragged() {
+            ui.visuals().selection.stroke.color
+        } else {
+            Color32::WHITE
+        };
+        let x = time_to_x(rect, duration, *time);
+        let handle_rect = Rect::from_center_size(pos2(x, rect.center().y), vec2(HANDLE_WIDTH, rect.height()));
+        ui.painter().rect_filled(handle_rect, 2.0, color);
+
+        response.on_hover_text(format_time(*time));
+        changed
+    }
+}
+
+fn time_to_x(rect: Rect, duration: u64, time: u64) -> f32 {
+    rect.left() + rect.width() * time as f32 / duration as f32
+}
+
+fn x_to_time(rect: Rect, duration: u64, x: f32) -> u64 {
+    let fraction = ((x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+    (fraction * duration as f32) as u64
+}
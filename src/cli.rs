@@ -0,0 +1,558 @@
+use std::{time::Duration, io::Write};
+
+use crate::get_media_info;
+use crate::engine::*;
+use crate::engine::animation::{AnimationFormat, AnimationOptions, Dither};
+use crate::engine::batch::{batch_jobs, DEFAULT_BATCH_EXTENSIONS};
+use crate::engine::codec::{AudioCodec, VideoCodec, DEFAULT_FLAC_COMPRESSION};
+use crate::engine::crop::Crop;
+use crate::engine::framerate::{describe_frame_rate, FrameRate, FrameRateMethod, FrameRateTarget};
+use crate::engine::loudness::Loudnorm;
+use crate::engine::scale::{ScaleFit, ScaleSize, Scaling};
+use crate::engine::subtitles::{BurnIn, SubtitleTracks, Subtitles};
+use crate::engine::thumbnail::{ImageFormat, ThumbnailKind, ThumbnailOptions};
+use crate::engine::trim::{parse_time, CutMode, Trim};
+
+const QUALITY_NAMES: &[&str] = &[ "original", "good", "medium", "bad", "poop" ];
+
+const USAGE: &str = "\
+Usage: compressor <MODE> <INPUT> [OPTIONS]
+
+Modes:
+    audio                    Convert the input file (or every file in the input directory) to mp3
+                             (or the format of the selected --audio-codec)
+    video                    Convert the input file (or every file in the input directory) to mp4
+                             (webm with the vp9 codec)
+    download                 Download the media from the provided link
+    thumbnail                Save a frame (or a contact sheet) of the video as an image
+    gif                      Save the video (or its trimmed part) as an animated GIF
+    webp                     Save the video (or its trimmed part) as an animated WebP
+
+Options:
+    -o, --output <PATH>          Output file (by default <input>-new.<ext> next to the input)
+                                 or the output directory when the input is a directory
+    -s, --size <MB>              Approximate size of the output file, overrides quality options.
+                                 The animations get a lower frame rate and width until they fit
+        --ratio <RATIO>          Video to audio bitrate ratio used with --size (default 10)
+        --verify-size            Encode again with a lower bitrate when the output exceeds --size
+    -r, --resolution <RES>       Output resolution: the short side of the video (1080p, 720p, 480p, ...)
+                                 or the exact size, for example 1280x720
+        --fit <FIT>              How the video is placed into the exact size: fit (default), fill
+                                 (cropped) or pad (black bars)
+        --upscale                Allow the resolution to be higher than the resolution of the source
+        --crop                   Detect the black bars of the video and crop them off before the scaling
+    -p, --preset <PRESET>        Compression preset: veryslow, slow, medium, fast, ultrafast
+    -c, --codec <CODEC>          Video codec: h264, h265, vp9, av1, svt-av1 (default h264)
+        --audio-codec <CODEC>    Audio codec of the audio mode: mp3, aac, opus, vorbis, flac, wav
+                                 (default mp3)
+        --compression-level <N>  FLAC compression level from 0 (fastest) to 12 (smallest), default 5
+    -a, --audio-quality <Q>      Audio quality: original, good, medium, bad, poop
+    -v, --video-quality <Q>      Video quality: original, good, medium, bad, poop
+        --rate-mode <MODE>       What the video quality selects: quality (constant quality, default)
+                                 or bitrate (fixed bitrate)
+        --start <TIME>           Start of the kept part of the media (seconds, MM:SS or HH:MM:SS)
+        --end <TIME>             End of the kept part of the media
+        --duration <TIME>        Duration of the kept part of the media, instead of --end
+        --fast-cut               Cut by copying the streams instead of encoding them again. It is fast,
+                                 but the cut starts at the closest keyframe and nothing gets compressed
+        --normalize              Normalize the loudness of the audio (EBU R128, two passes)
+        --loudness <LUFS>        Integrated loudness of --normalize, from -70 to -5 (default -23)
+        --true-peak <DBTP>       Maximum true peak of --normalize, from -9 to 0 (default -1)
+        --lra <LU>               Loudness range of --normalize, from 1 to 20 (default 7)
+        --subs <TRACKS>          Keep the subtitle tracks in the output: all or the stream indexes of the
+                                 tracks separated with commas (by default the subtitles are dropped)
+        --burn-subs <SUBS>       Burn the subtitles into the video: the stream index of a subtitle track
+                                 or the path to a subtitle file (.srt, .ass)
+        --extract-subs <TRACKS>  Extract the subtitle tracks into separate files next to the output
+        --time <TIME>            Time of the frame saved in the thumbnail mode (by default the middle)
+        --sheet <COLSxROWS>      Save a contact sheet with a grid of frames instead, for example 4x3
+        --timestamps             Draw the time of every frame of the contact sheet
+        --image-format <FORMAT>  Image format of the thumbnail mode: png, jpeg, webp (default png)
+        --width <PX>             Width of the frame (of every frame of the contact sheet),
+                                 or the width of the animation (default 480)
+        --fps <FPS>              Frame rate of the animation, from 1 to 60 (default 15), or the frame
+                                 rate the video is converted to
+        --max-fps <FPS>          Lower the frame rate of the video when it is higher than this
+        --drop-frames            Lower the frame rate by dropping the frames instead of the fps filter,
+                                 the timing of the kept frames does not change
+        --cfr                    Force the constant frame rate of the video
+        --dither <DITHER>        Dithering of the GIF: none, bayer, floyd, sierra (default sierra)
+        --plays <N>              How many times the animation is played, 0 loops it forever (default)
+    -R, --recursive              Also convert the files in the subdirectories of the input directory
+    -e, --extensions <LIST>      Extensions of the files converted from the input directory,
+                                 separated with commas
+    -h, --help                   Print this message
+
+Running the compressor without any arguments opens the GUI (when built with the `gui` feature).";
+
+/// Runs the compressor without the GUI. Returns the exit code of the program.
+pub fn run(args: &[String]) -> i32 {
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return 0;
+    }
+
+    if args.is_empty() {
+        eprintln!("{USAGE}");
+        return 2;
+    }
+
+    let jobs = match parse_args(args) {
+        Ok(jobs) => jobs,
+        Err(message) => {
+            eprintln!("ERROR: {message}");
+            eprintln!();
+            eprintln!("{USAGE}");
+            return 2;
+        }
+    };
+
+    let mut failed = 0;
+    for (i, job) in jobs.iter().enumerate() {
+        if jobs.len() > 1 {
+            eprintln!("[{}/{}] {}", i + 1, jobs.len(), job.input());
+        }
+
+        let status = run_job(job, i, jobs.len());
+        if status != JobStatus::Finished {
+            failed += 1;
+        }
+    }
+
+    if jobs.len() > 1 {
+        eprintln!("Converted {} of {} files", jobs.len() - failed, jobs.len());
+    }
+
+    if failed == 0 { 0 } else { 1 }
+}
+
+/// Runs a single job and prints its progress. With many jobs the overall progress is printed as well.
+fn run_job(job: &CompressorJob, index: usize, job_count: usize) -> JobStatus {
+    if let CompressorJob::Convert(convertion) = job {
+        let media_info = get_media_info(&convertion.input_file);
+        let bitrates = media_info.as_ref()
+            .and_then(|media_info| convertion.target_bitrates(media_info).ok().flatten());
+
+        if let Some(bitrates) = bitrates {
+            eprintln!("Target bitrates: {bitrates}");
+        }
+
+        let source_fps = media_info.as_ref()
+            .and_then(|media_info| media_info.first_video())
+            .and_then(describe_frame_rate);
+
+        if let (AppMode::Video, Some(source_fps)) = (convertion.mode, source_fps) {
+            eprintln!("Source frame rate: {source_fps}");
+        }
+    }
+
+    let handle = job.start();
+    let mut messages = Vec::new();
+
+    let status = loop {
+        let Some(event) = handle.wait_event(Duration::from_millis(250)) else {
+            continue;
+        };
+
+        match event {
+            JobEvent::Finished(status) => break status,
+            JobEvent::Message(log) => messages.push(log),
+            JobEvent::Progress(progress) => {
+                let overall = if job_count > 1 {
+                    let fraction = (index as f32 + progress.fraction) / job_count as f32;
+                    format!(" | overall [{:.2}%]", fraction * 100.0)
+                } else {
+                    String::new()
+                };
+
+                // Trailing spaces clear the leftovers of a previous, longer line
+                eprint!("\r{}: [{:.2}%] {}{overall}    ", job.name(), progress.fraction * 100.0, progress.details());
+                let _ = std::io::stderr().flush();
+            }
+        }
+    };
+    eprintln!();
+
+    for log in messages {
+        match log.kind {
+            MessageKind::Error   => eprintln!("ERROR: {}", log.text),
+            MessageKind::Warning => eprintln!("WARNING: {}", log.text),
+            _                    => eprintln!("INFO: {}", log.text),
+        }
+    }
+
+    status
+}
+
+/// Creates the jobs from the command line arguments (without the program name). A directory input creates
+/// a job for every media file in it.
+pub fn parse_args(args: &[String]) -> Result<Vec<CompressorJob>, &'static str> {
+    let mut args = args.iter();
+
+    let mode_name = args.next().map(String::as_str);
+    let mode = match mode_name {
+        Some("audio")    => AppMode::Audio,
+        Some("video")    => AppMode::Video,
+        Some("download") => AppMode::Download,
+        Some("thumbnail") => AppMode::Thumbnail,
+        Some("gif" | "webp") => AppMode::Animation,
+        Some(_) => return Err("Unknown mode. Expected one of: audio, video, download, thumbnail, gif, webp."),
+        None    => return Err("No mode was provided."),
+    };
+
+    let mut input = None;
+    let mut output = String::new();
+    let mut output_file_size = None;
+    let mut bitrate_ratio = 10.0;
+    let mut verify_output_size = false;
+    let mut scale_size = None;
+    let mut scale_fit = ScaleFit::default();
+    let mut upscale = false;
+    let mut crop = Crop::None;
+    let mut preset = 2;
+    let mut video_codec = VideoCodec::default();
+    let mut audio_codec = AudioCodec::default();
+    let mut flac_compression = DEFAULT_FLAC_COMPRESSION;
+    let mut audio_quality = Quality::default();
+    let mut video_quality = Quality::default();
+    let mut video_rate_mode = VideoRateMode::default();
+    let mut recursive = false;
+    let mut trim = Trim::default();
+    let mut duration = None;
+    let mut normalize = false;
+    let mut loudnorm = Loudnorm::default();
+    let mut subtitles = Subtitles::default();
+    let mut thumbnail_options = ThumbnailOptions::default();
+    let mut frame_time = None;
+    let mut timestamps = false;
+    let mut animation_options = AnimationOptions::default();
+    let mut fps = None;
+    let mut max_fps = None;
+    let mut frame_rate = FrameRate::default();
+    if mode_name == Some("webp") {
+        animation_options.format = AnimationFormat::Webp;
+    }
+    let mut extensions = String::from(DEFAULT_BATCH_EXTENSIONS);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                output = args.next().ok_or("Missing value for --output.")?.clone();
+            }
+            "-s" | "--size" => {
+                let value = args.next().ok_or("Missing value for --size.")?;
+                let Ok(size) = value.trim().parse::<f32>() else {
+                    return Err("Failed to read the output file size.");
+                };
+                output_file_size = Some(size);
+            }
+            "--ratio" => {
+                let value = args.next().ok_or("Missing value for --ratio.")?;
+                let Ok(ratio) = value.trim().parse::<f32>() else {
+                    return Err("Failed to read the bitrate ratio.");
+                };
+                if !(1.0..=20.0).contains(&ratio) {
+                    return Err("Bitrate ratio must be between 1 and 20.");
+                }
+                bitrate_ratio = ratio;
+            }
+            "--verify-size" => verify_output_size = true,
+            "-r" | "--resolution" => {
+                let value = args.next().ok_or("Missing value for --resolution.")?;
+                let size = match value.trim().split_once('x') {
+                    Some((width, height)) => width.parse().ok().zip(height.parse().ok())
+                        .map(|(width, height)| ScaleSize::Box { width, height }),
+                    None => value.trim().trim_end_matches('p').parse().ok().map(ScaleSize::ShortSide),
+                };
+                let Some(size) = size else {
+                    return Err("Failed to read the resolution. Expected the short side (720p) or the size (1280x720).");
+                };
+                scale_size = Some(size);
+            }
+            "--fit" => {
+                let value = args.next().ok_or("Missing value for --fit.")?;
+                let Some(fit) = ScaleFit::from_name(value) else {
+                    return Err("Unknown fit. Expected one of: fit, fill, pad.");
+                };
+                scale_fit = fit;
+            }
+            "--upscale" => upscale = true,
+            "--crop" => crop = Crop::Detect,
+            "-p" | "--preset" => {
+                let value = args.next().ok_or("Missing value for --preset.")?;
+                let Some(index) = PRESET_FFMPEG_STRINGS.iter().position(|p| p == value) else {
+                    return Err("Unknown preset. Expected one of: veryslow, slow, medium, fast, ultrafast.");
+                };
+                preset = index;
+            }
+            "-c" | "--codec" => {
+                let value = args.next().ok_or("Missing value for --codec.")?;
+                let Some(codec) = VideoCodec::from_name(value) else {
+                    return Err("Unknown codec. Expected one of: h264, h265, vp9, av1, svt-av1.");
+                };
+                video_codec = codec;
+            }
+            "--audio-codec" => {
+                let value = args.next().ok_or("Missing value for --audio-codec.")?;
+                let Some(codec) = AudioCodec::from_name(value) else {
+                    return Err("Unknown audio codec. Expected one of: mp3, aac, opus, vorbis, flac, wav.");
+                };
+                audio_codec = codec;
+            }
+            "--compression-level" => {
+                let value = args.next().ok_or("Missing value for --compression-level.")?;
+                let Some(level) = value.trim().parse::<u8>().ok().filter(|level| *level <= 12) else {
+                    return Err("Compression level must be a number between 0 and 12.");
+                };
+                flac_compression = level;
+            }
+            "-a" | "--audio-quality" => {
+                let value = args.next().ok_or("Missing value for --audio-quality.")?;
+                audio_quality = parse_quality(value)?;
+            }
+            "-v" | "--video-quality" => {
+                let value = args.next().ok_or("Missing value for --video-quality.")?;
+                video_quality = parse_quality(value)?;
+            }
+            "--rate-mode" => {
+                video_rate_mode = match args.next().ok_or("Missing value for --rate-mode.")?.as_str() {
+                    "quality" => VideoRateMode::QualityFirst,
+                    "bitrate" => VideoRateMode::BitrateFirst,
+                    _ => return Err("Unknown rate mode. Expected one of: quality, bitrate."),
+                };
+            }
+            "--start" => {
+                let value = args.next().ok_or("Missing value for --start.")?;
+                trim.start = Some(parse_time(value).ok_or("Failed to read the --start time.")?);
+            }
+            "--end" => {
+                let value = args.next().ok_or("Missing value for --end.")?;
+                trim.end = Some(parse_time(value).ok_or("Failed to read the --end time.")?);
+            }
+            "--duration" => {
+                let value = args.next().ok_or("Missing value for --duration.")?;
+                duration = Some(parse_time(value).ok_or("Failed to read the --duration time.")?);
+            }
+            "--fast-cut" => trim.cut_mode = CutMode::Fast,
+            "--normalize" => normalize = true,
+            "--loudness" => {
+                let value = args.next().ok_or("Missing value for --loudness.")?;
+                loudnorm.integrated = value.trim().parse().map_err(|_| "Failed to read the target loudness.")?;
+            }
+            "--true-peak" => {
+                let value = args.next().ok_or("Missing value for --true-peak.")?;
+                loudnorm.true_peak = value.trim().parse().map_err(|_| "Failed to read the target true peak.")?;
+            }
+            "--lra" => {
+                let value = args.next().ok_or("Missing value for --lra.")?;
+                loudnorm.range = value.trim().parse().map_err(|_| "Failed to read the target loudness range.")?;
+            }
+            "--subs" => {
+                let value = args.next().ok_or("Missing value for --subs.")?;
+                subtitles.keep = parse_tracks(value)?;
+            }
+            "--burn-subs" => {
+                let value = args.next().ok_or("Missing value for --burn-subs.")?;
+                subtitles.burn = match value.trim().parse::<usize>() {
+                    Ok(track) => BurnIn::Track(track),
+                    Err(_) => BurnIn::File(value.clone()),
+                };
+            }
+            "--extract-subs" => {
+                let value = args.next().ok_or("Missing value for --extract-subs.")?;
+                subtitles.extract = parse_tracks(value)?;
+            }
+            "--time" => {
+                let value = args.next().ok_or("Missing value for --time.")?;
+                frame_time = Some(parse_time(value).ok_or("Failed to read the --time.")?);
+            }
+            "--sheet" => {
+                let value = args.next().ok_or("Missing value for --sheet.")?;
+                let sheet = value.split_once('x').and_then(|(columns, rows)| {
+                    Some((columns.trim().parse().ok()?, rows.trim().parse().ok()?))
+                });
+                let Some((columns, rows)) = sheet else {
+                    return Err("Failed to read the contact sheet size. Expected columns and rows, for example 4x3.");
+                };
+                thumbnail_options.kind = ThumbnailKind::ContactSheet { columns, rows, timestamps: false };
+            }
+            "--timestamps" => timestamps = true,
+            "--image-format" => {
+                let value = args.next().ok_or("Missing value for --image-format.")?;
+                let Some(format) = ImageFormat::from_name(value) else {
+                    return Err("Unknown image format. Expected one of: png, jpeg, webp.");
+                };
+                thumbnail_options.format = format;
+            }
+            "--width" => {
+                let value = args.next().ok_or("Missing value for --width.")?;
+                let Ok(width) = value.trim().parse::<u32>() else {
+                    return Err("Failed to read the width.");
+                };
+                thumbnail_options.width = width;
+                animation_options.width = width;
+            }
+            "--fps" => {
+                let value = args.next().ok_or("Missing value for --fps.")?;
+                let Ok(value) = value.trim().parse::<f64>() else {
+                    return Err("Failed to read the frame rate.");
+                };
+                fps = Some(value);
+            }
+            "--max-fps" => {
+                let value = args.next().ok_or("Missing value for --max-fps.")?;
+                let Ok(value) = value.trim().parse::<f64>() else {
+                    return Err("Failed to read the frame rate.");
+                };
+                max_fps = Some(value);
+            }
+            "--drop-frames" => frame_rate.method = FrameRateMethod::Drop,
+            "--cfr" => frame_rate.constant = true,
+            "--dither" => {
+                let value = args.next().ok_or("Missing value for --dither.")?;
+                let Some(dither) = Dither::from_name(value) else {
+                    return Err("Unknown dithering. Expected one of: none, bayer, floyd, sierra.");
+                };
+                animation_options.dither = dither;
+            }
+            "--plays" => {
+                let value = args.next().ok_or("Missing value for --plays.")?;
+                let Ok(plays) = value.trim().parse::<u32>() else {
+                    return Err("Failed to read the number of plays.");
+                };
+                animation_options.plays = plays;
+            }
+            "-R" | "--recursive" => recursive = true,
+            "-e" | "--extensions" => {
+                extensions = args.next().ok_or("Missing value for --extensions.")?.clone();
+            }
+            _ if arg.starts_with('-') => return Err("Unknown option."),
+            _ if input.is_none() => input = Some(arg.clone()),
+            _ => return Err("Only one input can be provided."),
+        }
+    }
+
+    let Some(input) = input else {
+        return Err("No input was provided.");
+    };
+
+    // A single image can not be encoded again any smaller
+    if matches!(mode, AppMode::Thumbnail) && (output_file_size.is_some() || verify_output_size) {
+        return Err("The size of the thumbnail can not be selected, --size and --verify-size can not be used.");
+    }
+
+    if let Some(duration) = duration {
+        if trim.end.is_some() {
+            return Err("Only one of --end and --duration can be provided.");
+        }
+        trim.end = Some(trim.start.unwrap_or(0) + duration);
+    }
+    trim.validate()?;
+
+    if normalize {
+        loudnorm.validate()?;
+    }
+    let loudnorm = normalize.then_some(loudnorm);
+
+    let scaling = scale_size.map(|size| Scaling { size, fit: scale_fit, upscale });
+    if let Some(scaling) = scaling {
+        scaling.validate()?;
+    }
+
+    match &mut thumbnail_options.kind {
+        ThumbnailKind::Frame(time) => *time = frame_time,
+        ThumbnailKind::ContactSheet { timestamps: sheet_timestamps, .. } => {
+            if frame_time.is_some() {
+                return Err("Only one of --time and --sheet can be provided.");
+            }
+            *sheet_timestamps = timestamps;
+        }
+    }
+    thumbnail_options.validate()?;
+
+    frame_rate.target = match (fps, max_fps) {
+        (Some(_), Some(_)) => return Err("Only one of --fps and --max-fps can be provided."),
+        (Some(fps), None) if !matches!(mode, AppMode::Animation) => FrameRateTarget::Convert(fps),
+        (None, Some(max_fps)) => FrameRateTarget::Cap(max_fps),
+        _ => FrameRateTarget::Keep,
+    };
+    frame_rate.validate()?;
+
+    if matches!(mode, AppMode::Animation) {
+        if let Some(fps) = fps {
+            animation_options.fps = fps.round() as u32;
+        }
+        animation_options.validate()?;
+    }
+
+    if matches!(mode, AppMode::Download) {
+        let job = DownloadJob {
+            input_link: input,
+            output_file: output,
+        };
+        return Ok(vec![CompressorJob::Download(Box::new(job))]);
+    }
+
+    let input_path = std::path::Path::new(&input);
+    if !input_path.is_file() && !input_path.is_dir() {
+        return Err("Input path is incorrect, file does not exist.");
+    }
+
+    let mut job = ConvertionJob {
+        input_file: input.clone(),
+        output_file: String::new(),
+        mode,
+        audio_quality,
+        video_quality,
+        video_rate_mode,
+        scaling,
+        crop,
+        frame_rate,
+        preset,
+        video_codec,
+        audio_codec,
+        flac_compression,
+        output_file_size,
+        bitrate_ratio,
+        verify_output_size,
+        trim,
+        loudnorm,
+        subtitles,
+        advanced_options: ConvertOptions::default(),
+        thumbnail_options,
+        animation_options,
+    };
+
+    if input_path.is_dir() {
+        let jobs = batch_jobs(&job, input_path, &output, &extensions, recursive)?;
+        return Ok(jobs.into_iter().map(|job| CompressorJob::Convert(Box::new(job))).collect());
+    }
+
+    let Some(output_file) = output_path_for(&job.input_file, &output, &job.output_extension()) else {
+        return Err("Failed to construct output path for selected input.");
+    };
+    job.output_file = output_file;
+
+    Ok(vec![CompressorJob::Convert(Box::new(job))])
+}
+
+fn parse_quality(value: &str) -> Result<Quality, &'static str> {
+    let value = value.to_lowercase();
+    let Some(index) = QUALITY_NAMES.iter().position(|name| *name == value) else {
+        return Err("Unknown quality. Expected one of: original, good, medium, bad, poop.");
+    };
+    Ok(Quality::from_usize(index))
+}
+
+/// Parses "all" or the stream indexes separated with commas
+fn parse_tracks(value: &str) -> Result<SubtitleTracks, &'static str> {
+    if value.trim() == "all" {
+        return Ok(SubtitleTracks::All);
+    }
+
+    let tracks = value.split(',').map(|track| track.trim().parse::<usize>()).collect::<Result<Vec<_>, _>>();
+    let Ok(tracks) = tracks else {
+        return Err("Failed to read the subtitle tracks. Expected \"all\" or stream indexes separated with commas.");
+    };
+    Ok(SubtitleTracks::Selected(tracks))
+}
@@ -1,4 +1,4 @@
-use std::fs::create_dir_all;
+use std::fs::{create_dir_all, File};
 
 use eframe::egui::{self, Button};
 use directories_next::ProjectDirs;
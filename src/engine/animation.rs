@@ -0,0 +1,183 @@
+//! Animated GIF and WebP export. GIFs are limited to 256 colors, so they are encoded in two stages: the first
+//! pass generates the palette of the whole clip with `palettegen` and the second one maps the frames onto it
+//! with `paletteuse`, which looks much better than the generic palette used by ffmpeg otherwise.
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Webp,
+}
+
+pub const ANIMATION_FORMATS: &[AnimationFormat] = &[ AnimationFormat::Gif, AnimationFormat::Webp ];
+
+/// How the colors missing from the GIF palette are approximated
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum Dither {
+    None,
+    /// Ordered dithering, the scale (0 - 5) sets how visible the pattern is
+    Bayer(u8),
+    FloydSteinberg,
+    #[default]
+    Sierra,
+}
+
+pub const DITHERS: &[Dither] = &[ Dither::None, Dither::Bayer(3), Dither::FloydSteinberg, Dither::Sierra ];
+
+// The smallest frame rate and width the target size is allowed to lower the animation to
+pub const MIN_ANIMATION_FPS: u32 = 5;
+pub const MIN_ANIMATION_WIDTH: u32 = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationOptions {
+    pub format: AnimationFormat,
+    /// Frame rate of the animation, never higher than the frame rate of the video
+    pub fps: u32,
+    /// Width of the animation in px, never larger than the width of the video
+    pub width: u32,
+    /// Used only by the GIFs
+    pub dither: Dither,
+    /// How many times the animation is played, 0 loops it forever
+    pub plays: u32,
+}
+
+impl Default for AnimationOptions {
+    fn default() -> Self {
+        Self {
+            format: AnimationFormat::default(),
+            fps: 15,
+            width: 480,
+            dither: Dither::default(),
+            plays: 0,
+        }
+    }
+}
+
+impl AnimationFormat {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif  => "GIF",
+            AnimationFormat::Webp => "Animated WebP",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AnimationFormat::Gif  => "gif",
+            AnimationFormat::Webp => "webp",
+        }
+    }
+}
+
+impl Dither {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            Dither::None           => "None",
+            Dither::Bayer(_)       => "Bayer",
+            Dither::FloydSteinberg => "Floyd-Steinberg",
+            Dither::Sierra         => "Sierra",
+        }
+    }
+
+    /// Name used in the command line arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dither::None           => "none",
+            Dither::Bayer(_)       => "bayer",
+            Dither::FloydSteinberg => "floyd",
+            Dither::Sierra         => "sierra",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        DITHERS.iter().copied().find(|dither| dither.name() == name.to_lowercase())
+    }
+
+    /// Options of the paletteuse filter
+    fn filter_options(&self) -> String {
+        match self {
+            Dither::None           => String::from("dither=none"),
+            Dither::Bayer(scale)   => format!("dither=bayer:bayer_scale={}", scale.min(&5)),
+            Dither::FloydSteinberg => String::from("dither=floyd_steinberg"),
+            Dither::Sierra         => String::from("dither=sierra2_4a"),
+        }
+    }
+}
+
+impl AnimationOptions {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if !(1..=60).contains(&self.fps) {
+            return Err("Frame rate of the animation must be between 1 and 60.");
+        }
+        if !(16..=3840).contains(&self.width) {
+            return Err("Width of the animation must be between 16 and 3840.");
+        }
+        Ok(())
+    }
+
+    /// Copy of the options with the frame rate capped at the frame rate of the video (`source_fps`), the
+    /// frames are never duplicated
+    pub fn capped(&self, source_fps: Option<f64>) -> Self {
+        let fps = match source_fps {
+            Some(source_fps) if source_fps >= 1.0 => self.fps.min(source_fps.ceil() as u32),
+            _ => self.fps,
+        };
+        Self { fps, ..self.clone() }
+    }
+
+    /// Frame rate and scaling of the animation
+    fn frame_filters(&self) -> String {
+        // The quotes keep the comma of the expression from splitting the filter graph
+        format!("fps={},scale='min({},iw)':-2:flags=lanczos", self.fps, self.width)
+    }
+
+    /// Filter of the first pass of the GIF, writing the palette into a single image
+    pub fn palette_filter(&self) -> String {
+        format!("{},palettegen=stats_mode=diff", self.frame_filters())
+    }
+
+    /// Filter graph of the second pass of the GIF, the palette is the second input
+    pub fn gif_filter(&self) -> String {
+        format!(
+            "{}[frames];[frames][1:v]paletteuse={}:diff_mode=rectangle",
+            self.frame_filters(), self.dither.filter_options(),
+        )
+    }
+
+    /// Filter of the animated WebP, it has the full colors so no palette is needed
+    pub fn webp_filter(&self) -> String {
+        self.frame_filters()
+    }
+
+    /// Value of the -loop option. The GIF counts the repeats after the first play (-1 plays it once),
+    /// the WebP counts all of the plays.
+    pub fn loop_arg(&self) -> String {
+        match (self.format, self.plays) {
+            (_, 0) => String::from("0"),
+            (AnimationFormat::Gif, 1) => String::from("-1"),
+            (AnimationFormat::Gif, plays) => (plays - 1).to_string(),
+            (AnimationFormat::Webp, plays) => plays.to_string(),
+        }
+    }
+
+    /// Lowers the frame rate and the width so the animation gets about `factor` times smaller. The size
+    /// grows with the frame rate and the area of the frames, so the reduction is split evenly between the
+    /// frame rate and both dimensions. Whatever the frame rate can not take is taken by the width.
+    pub fn shrink(&mut self, factor: f32) {
+        let factor = factor.clamp(0.01, 1.0);
+
+        let fps = ((self.fps as f32 * factor.cbrt()).round() as u32).clamp(MIN_ANIMATION_FPS.min(self.fps), self.fps);
+        let width_factor = (factor * self.fps as f32 / fps as f32).sqrt();
+        let width = ((self.width as f32 * width_factor) as u32 & !1).clamp(MIN_ANIMATION_WIDTH.min(self.width), self.width);
+
+        self.fps = fps;
+        self.width = width;
+    }
+}
+
+/// Path of the palette used by the second pass of the GIF, unique for every job
+pub fn palette_path() -> std::path::PathBuf {
+    super::unique_temp_path("compressor-palette").with_extension("png")
+}
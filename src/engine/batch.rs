@@ -0,0 +1,123 @@
+//! Expanding a directory into one convertion job per media file.
+
+use std::path::{Path, PathBuf};
+
+use super::ConvertionJob;
+
+/// Files with these extensions are converted when a directory is provided as the input
+pub const DEFAULT_BATCH_EXTENSIONS: &str = "mp4, mkv, webm, mov, avi, flv, wmv, mp3, wav, flac, ogg, opus, m4a, aac";
+
+/// Splits "mp4, .MKV webm" into [ "mp4", "mkv", "webm" ]
+pub fn parse_extensions(filter: &str) -> Vec<String> {
+    filter
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+        .filter(|ext| !ext.is_empty())
+        .collect()
+}
+
+/// Output directory used when the user did not provide one: "<input dir>-new" next to the input directory
+pub fn default_batch_output_dir(input_dir: &Path) -> Option<PathBuf> {
+    // "." and ".." have no name of their own
+    let input_dir = if input_dir.file_name().is_some() { input_dir.to_path_buf() } else { canonical(input_dir) };
+    let dir_name = input_dir.file_name()?.to_str()?;
+    Some(input_dir.with_file_name(format!("{dir_name}-new")))
+}
+
+/// Absolute path with the links resolved, so "out" and "./out" are the same directory. Paths that do not
+/// exist yet are kept as they are.
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Finds all files in the directory matching the extensions, sorted by their path. The `skip`
+/// directory is never entered, so the output directory placed inside of the input is not converted again.
+pub fn collect_media_files(input_dir: &Path, extensions: &[String], recursive: bool, skip: &Path) -> Vec<PathBuf> {
+    let skip = canonical(skip);
+    let mut files = Vec::new();
+    let mut directories = vec![input_dir.to_path_buf()];
+
+    while let Some(directory) = directories.pop() {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if file_type.is_dir() {
+                if recursive && canonical(&path) != skip {
+                    directories.push(path);
+                }
+                continue;
+            }
+
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default().to_lowercase();
+            if extensions.contains(&extension) {
+                files.push(path);
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// Places the file in the output directory at the same relative path it had in the input directory.
+pub fn batch_output_path(input_dir: &Path, output_dir: &Path, file: &Path, extension: &str) -> Option<PathBuf> {
+    let relative = file.strip_prefix(input_dir).ok()?;
+    let mut output = output_dir.join(relative);
+    output.set_extension(extension);
+    Some(output)
+}
+
+/// Creates a copy of the template job for every matching file of the input directory. The converted
+/// files are placed in the output directory (by default "<input dir>-new") at the same relative paths.
+pub fn batch_jobs(
+    template: &ConvertionJob, input_dir: &Path, output_dir: &str, extensions: &str, recursive: bool
+) -> Result<Vec<ConvertionJob>, &'static str> {
+    let output_dir = if output_dir.trim().is_empty() {
+        let Some(output_dir) = default_batch_output_dir(input_dir) else {
+            return Err("Failed to construct output directory for selected input.");
+        };
+        output_dir
+    } else {
+        PathBuf::from(output_dir.trim())
+    };
+
+    if output_dir.is_file() {
+        return Err("Output path must be a directory when the input is a directory.");
+    }
+
+    // The converted files would get the paths of the originals, ffmpeg would overwrite them
+    if canonical(&output_dir) == canonical(input_dir) {
+        return Err("Output directory must be different from the input directory.");
+    }
+
+    let extensions = parse_extensions(extensions);
+    if extensions.is_empty() {
+        return Err("No file extensions were provided for the directory convertion.");
+    }
+
+    let files = collect_media_files(input_dir, &extensions, recursive, &output_dir);
+    if files.is_empty() {
+        return Err("No files matching the selected extensions were found in the directory.");
+    }
+
+    let mut jobs = Vec::new();
+    for file in files {
+        let mut job = template.clone();
+        job.input_file = String::from(file.to_string_lossy());
+
+        let Some(output_file) = batch_output_path(input_dir, &output_dir, &file, &job.output_extension()) else {
+            continue;
+        };
+        job.output_file = String::from(output_file.to_string_lossy());
+        jobs.push(job);
+    }
+
+    Ok(jobs)
+}
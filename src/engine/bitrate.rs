@@ -0,0 +1,196 @@
+//! Splitting the bitrate budget of the target-size mode between the video and the audio.
+
+use std::ops::RangeInclusive;
+
+use crate::MediaInfo;
+use super::{AppMode, ConvertionJob, Quality, VideoRateMode};
+
+/// Part of the output file taken by the container (headers, indexes, interleaving), not by the streams
+pub const CONTAINER_OVERHEAD: f32 = 0.02;
+
+/// Audio bitrates (kbit/s) that still make sense for the AAC encoder used in mp4 files
+pub const AAC_BITRATE_RANGE: RangeInclusive<u32> = 32..=320;
+/// Audio bitrates (kbit/s) supported by the Opus encoder used in webm files
+pub const OPUS_BITRATE_RANGE: RangeInclusive<u32> = 6..=510;
+/// Audio bitrates (kbit/s) of the Vorbis encoder, below the minimum it fails with the stereo audio
+pub const VORBIS_BITRATE_RANGE: RangeInclusive<u32> = 48..=480;
+/// Audio bitrates (kbit/s) supported by the mp3 encoder
+pub const MP3_BITRATE_RANGE: RangeInclusive<u32> = 8..=320;
+
+/// Video bitrates (kbit/s) below this value produce nothing but a blocky mess
+pub const MIN_VIDEO_BITRATE: u32 = 16;
+
+/// Video bitrates (kbit/s) selected by the quality levels stay in this range, unless the source is smaller
+pub const QUALITY_VIDEO_BITRATE_RANGE: RangeInclusive<u32> = 100..=50_000;
+
+// Bitrates (kbit/s) assumed when the bitrate of the source stream is unknown
+pub const FALLBACK_VIDEO_BITRATE: u32 = 2500;
+pub const FALLBACK_AUDIO_BITRATE: u32 = 128;
+
+/// How many times the convertion is run when the output keeps exceeding the selected size
+pub const MAX_SIZE_ATTEMPTS: u32 = 3;
+/// The encoders rarely hit the bitrate exactly, so every retry aims a bit lower than the measured correction
+pub const SIZE_RETRY_MARGIN: f32 = 0.97;
+
+/// Bitrates in kbit/s, None for the streams that are not encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetBitrates {
+    pub video: Option<u32>,
+    pub audio: Option<u32>,
+}
+
+impl std::fmt::Display for TargetBitrates {
+    /// For example: "Video: 1234 kbit/s, Audio: 123 kbit/s"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.video, self.audio) {
+            (Some(video), Some(audio)) => write!(f, "Video: {video} kbit/s, Audio: {audio} kbit/s"),
+            (Some(video), None)        => write!(f, "Video: {video} kbit/s"),
+            (None, Some(audio))        => write!(f, "Audio: {audio} kbit/s"),
+            (None, None)               => Ok(()),
+        }
+    }
+}
+
+/// Splits the bitrate needed to fit the media into `size_mb` (in MB, 1 MB = 1 000 000 bytes).
+/// `ratio` is the video to audio bitrate ratio, so the audio gets 1 / (ratio + 1) of the budget.
+/// The audio bitrate is clamped to the `audio_range`, and the video gets everything that is left.
+pub fn split_bitrate(
+    size_mb: f32, duration_ms: u64, ratio: f32, has_video: bool, has_audio: bool, audio_range: RangeInclusive<u32>,
+) -> Result<TargetBitrates, &'static str> {
+    if size_mb <= 0.0 || !size_mb.is_finite() {
+        return Err("Output file size must be greater than zero.");
+    }
+
+    if duration_ms == 0 {
+        return Err("Failed to retreive media length from the provided file.");
+    }
+
+    let duration_in_secs = duration_ms as f32 / 1000.0;
+    let total_bitrate = size_mb * 8000.0 / duration_in_secs;
+    let usable_bitrate = total_bitrate * (1.0 - CONTAINER_OVERHEAD);
+
+    let audio = if !has_audio {
+        None
+    } else if has_video {
+        let audio_bitrate = usable_bitrate / (ratio.max(0.0) + 1.0);
+        Some((audio_bitrate as u32).clamp(*audio_range.start(), *audio_range.end()))
+    } else {
+        if (usable_bitrate as u32) < *audio_range.start() {
+            return Err("Output file size is too small for the length of the media.");
+        }
+        Some((usable_bitrate as u32).min(*audio_range.end()))
+    };
+
+    let video = if has_video {
+        let video_bitrate = usable_bitrate - audio.unwrap_or(0) as f32;
+        if video_bitrate < MIN_VIDEO_BITRATE as f32 {
+            return Err("Output file size is too small for the length of the media.");
+        }
+        Some(video_bitrate as u32)
+    } else {
+        None
+    };
+
+    Ok(TargetBitrates { video, audio })
+}
+
+/// Bitrate of the quality level, relative to the `source` bitrate (`fallback` when unknown).
+/// The result is clamped to the `range`, but it never exceeds the source bitrate unless the source is below
+/// the range, so small files do not get inflated.
+pub fn quality_bitrate(quality: Quality, source: Option<u32>, fallback: u32, range: RangeInclusive<u32>) -> u32 {
+    let source = source.unwrap_or(fallback);
+    let bitrate = (source as f32 * quality.bitrate_fraction()) as u32;
+    bitrate.clamp(*range.start(), *range.end()).min(source.max(*range.start()))
+}
+
+/// Bitrate of the first video stream in kbit/s. Streams of mkv and webm files usually do not report it,
+/// in that case it is estimated from the overall bitrate without the audio streams.
+pub fn source_video_bitrate(media_info: &MediaInfo) -> Option<u32> {
+    let video = media_info.first_video()?;
+    if let Some(bit_rate) = video.bit_rate {
+        return Some((bit_rate / 1000) as u32);
+    }
+
+    let audio_bit_rate: u64 = media_info.audio_streams()
+        .map(|stream| stream.bit_rate.unwrap_or(FALLBACK_AUDIO_BITRATE as u64 * 1000))
+        .sum();
+
+    let bit_rate = media_info.bit_rate?.checked_sub(audio_bit_rate)?;
+    (bit_rate >= 1000).then_some((bit_rate / 1000) as u32)
+}
+
+/// Bitrate of the first audio stream in kbit/s. For files with nothing but the audio the overall bitrate
+/// is used when the stream does not report one.
+pub fn source_audio_bitrate(media_info: &MediaInfo) -> Option<u32> {
+    let audio = media_info.first_audio()?;
+    let bit_rate = match audio.bit_rate {
+        Some(bit_rate) => bit_rate,
+        None if media_info.first_video().is_none() => media_info.bit_rate?,
+        None => return None,
+    };
+    Some((bit_rate / 1000) as u32)
+}
+
+impl ConvertionJob {
+    /// Bitrates selected by the quality levels, relative to the bitrates of the source streams. None for
+    /// the streams encoded without a bitrate (constant quality video, VBR and lossless audio).
+    pub fn quality_bitrates(&self, media_info: &MediaInfo) -> TargetBitrates {
+        let audio_codec = match self.mode {
+            AppMode::Video => self.video_codec.audio_codec(),
+            AppMode::Audio => self.audio_codec,
+            // The Advanced mode has its own bitrates, the other modes do not encode any media
+            AppMode::Advanced | AppMode::Download | AppMode::Thumbnail | AppMode::Animation => return TargetBitrates { video: None, audio: None },
+        };
+
+        let audio = match audio_codec.bitrate_range() {
+            Some(range) if media_info.first_audio().is_some() && audio_codec.vbr_level(self.audio_quality).is_none() => {
+                let source = source_audio_bitrate(media_info);
+                Some(quality_bitrate(self.audio_quality, source, FALLBACK_AUDIO_BITRATE, range))
+            }
+            _ => None,
+        };
+
+        let video_by_bitrate = matches!(self.mode, AppMode::Video) && self.video_rate_mode == VideoRateMode::BitrateFirst;
+        let video = if video_by_bitrate && media_info.first_video().is_some() {
+            let source = source_video_bitrate(media_info);
+            Some(quality_bitrate(self.video_quality, source, FALLBACK_VIDEO_BITRATE, QUALITY_VIDEO_BITRATE_RANGE))
+        } else {
+            None
+        };
+
+        TargetBitrates { video, audio }
+    }
+
+    /// Bitrates used to fit the output into the `output_file_size`, None when no size was selected.
+    pub fn target_bitrates(&self, media_info: &MediaInfo) -> Result<Option<TargetBitrates>, &'static str> {
+        let Some(size_mb) = self.output_file_size else {
+            return Ok(None);
+        };
+
+        let duration_ms = self.trimmed_duration(media_info)?;
+
+        let has_audio = media_info.first_audio().is_some();
+        let bitrates = match self.mode {
+            AppMode::Video => {
+                let has_video = media_info.first_video().is_some();
+                let Some(audio_range) = self.video_codec.audio_codec().bitrate_range() else {
+                    return Err("Selected codec does not support the target size.");
+                };
+                split_bitrate(size_mb, duration_ms, self.bitrate_ratio, has_video, has_audio, audio_range)?
+            }
+            AppMode::Audio => {
+                if !has_audio {
+                    return Err("The provided file does not contain any audio.");
+                }
+                let Some(audio_range) = self.audio_codec.bitrate_range() else {
+                    return Err("Lossless audio codecs can not be used with the target size.");
+                };
+                split_bitrate(size_mb, duration_ms, self.bitrate_ratio, false, true, audio_range)?
+            }
+            // Bitrates of the Advanced mode are always selected by hand
+            AppMode::Advanced | AppMode::Download | AppMode::Thumbnail | AppMode::Animation => return Ok(None),
+        };
+
+        Ok(Some(bitrates))
+    }
+}
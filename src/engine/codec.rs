@@ -0,0 +1,325 @@
+//! Video and audio encoders supported by the compressor. Every encoder names its speed presets, quality
+//! scale and two-pass options differently, so the ffmpeg arguments are translated here.
+
+use std::{ops::RangeInclusive, path::{Path, PathBuf}};
+
+use super::{ADVANCED_PRESET_STRINGS, Quality};
+use super::bitrate::{AAC_BITRATE_RANGE, MP3_BITRATE_RANGE, OPUS_BITRATE_RANGE, VORBIS_BITRATE_RANGE};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    H265,
+    Vp9,
+    /// AV1 encoded with the reference encoder (libaom), slow but efficient
+    Av1,
+    /// AV1 encoded with SVT-AV1, much faster than libaom
+    SvtAv1,
+}
+
+pub const VIDEO_CODECS: &[VideoCodec] = &[
+    VideoCodec::H264, VideoCodec::H265, VideoCodec::Vp9, VideoCodec::Av1, VideoCodec::SvtAv1,
+];
+
+// Speed settings of the encoders, one for each of the ADVANCED_PRESET_STRINGS (from ultrafast to placebo)
+const VP9_CPU_USED:    [u8; 10] = [ 8, 7, 6, 5, 4, 3, 2, 1, 0, 0 ];
+const AOM_CPU_USED:    [u8; 10] = [ 8, 8, 7, 6, 6, 5, 4, 3, 2, 1 ];
+const SVTAV1_PRESETS:  [u8; 10] = [ 12, 12, 11, 10, 9, 8, 6, 5, 4, 2 ];
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum AudioCodec {
+    #[default]
+    Mp3,
+    Aac,
+    Opus,
+    Vorbis,
+    Flac,
+    Wav,
+}
+
+pub const AUDIO_CODECS: &[AudioCodec] = &[
+    AudioCodec::Mp3, AudioCodec::Aac, AudioCodec::Opus, AudioCodec::Vorbis, AudioCodec::Flac, AudioCodec::Wav,
+];
+
+/// Compression level of FLAC used by ffmpeg when none is selected
+pub const DEFAULT_FLAC_COMPRESSION: u8 = 5;
+
+/// Tunes of the ADVANCED_TUNE_STRINGS understood by libx265
+const X265_TUNES: &[&str] = &[ "grain", "animation", "fastdecode", "zerolatency" ];
+
+/// Sample rates (in Hz) supported by the Opus encoder used for the audio in WebM files
+pub const OPUS_SAMPLE_RATES: &[u32] = &[ 8000, 12000, 16000, 24000, 48000 ];
+/// Sample rates (in Hz) of the MPEG audio, the only ones accepted by LAME
+pub const MP3_SAMPLE_RATES: &[u32] = &[ 8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000 ];
+
+impl VideoCodec {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoCodec::H264   => "H.264 (x264)",
+            VideoCodec::H265   => "H.265 (x265)",
+            VideoCodec::Vp9    => "VP9",
+            VideoCodec::Av1    => "AV1 (libaom)",
+            VideoCodec::SvtAv1 => "AV1 (SVT-AV1)",
+        }
+    }
+
+    /// Name used in the command line arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            VideoCodec::H264   => "h264",
+            VideoCodec::H265   => "h265",
+            VideoCodec::Vp9    => "vp9",
+            VideoCodec::Av1    => "av1",
+            VideoCodec::SvtAv1 => "svt-av1",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        VIDEO_CODECS.iter().copied().find(|codec| codec.name() == name.to_lowercase())
+    }
+
+    /// Name of the ffmpeg encoder
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            VideoCodec::H264   => "libx264",
+            VideoCodec::H265   => "libx265",
+            VideoCodec::Vp9    => "libvpx-vp9",
+            VideoCodec::Av1    => "libaom-av1",
+            VideoCodec::SvtAv1 => "libsvtav1",
+        }
+    }
+
+    /// Extension of the container the codec is stored in
+    pub fn container(&self) -> &'static str {
+        match self {
+            VideoCodec::Vp9 => "webm",
+            _ => "mp4",
+        }
+    }
+
+    /// Audio codec accepted by the container of the codec
+    pub fn audio_codec(&self) -> AudioCodec {
+        match self.container() {
+            "webm" => AudioCodec::Opus,
+            _ => AudioCodec::Aac,
+        }
+    }
+
+    /// Arguments selecting the speed of the encoder. The `preset` is one of the ADVANCED_PRESET_STRINGS
+    /// (or PRESET_FFMPEG_STRINGS) and is mapped to the closest setting of the encoder.
+    pub fn preset_args(&self, preset: &str) -> Vec<String> {
+        let index = ADVANCED_PRESET_STRINGS.iter().position(|p| *p == preset).unwrap_or(5);
+
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 => vec![ "-preset".into(), preset.into() ],
+            VideoCodec::Vp9 => {
+                // cpu-used above 5 is only accepted by the realtime deadline
+                let cpu_used = VP9_CPU_USED[index];
+                let deadline = if cpu_used > 5 { "realtime" } else { "good" };
+                vec![
+                    "-deadline".into(), deadline.into(), "-cpu-used".into(), cpu_used.to_string(), "-row-mt".into(), "1".into(),
+                ]
+            }
+            VideoCodec::Av1 => {
+                // Same as above, the good quality usage stops at 6
+                let cpu_used = AOM_CPU_USED[index];
+                let usage = if cpu_used > 6 { "realtime" } else { "good" };
+                vec![
+                    "-usage".into(), usage.into(), "-cpu-used".into(), cpu_used.to_string(), "-row-mt".into(), "1".into(),
+                ]
+            }
+            VideoCodec::SvtAv1 => vec![ "-preset".into(), SVTAV1_PRESETS[index].to_string() ],
+        }
+    }
+
+    pub fn supports_tune(&self, tune: &str) -> bool {
+        match self {
+            VideoCodec::H264 => true,
+            VideoCodec::H265 => tune.is_empty() || X265_TUNES.contains(&tune),
+            _ => tune.is_empty(),
+        }
+    }
+
+    /// Highest (worst) value of the constant quality scale
+    pub fn max_crf(&self) -> u8 {
+        match self {
+            VideoCodec::H264 | VideoCodec::H265 => 51,
+            _ => 63,
+        }
+    }
+
+    /// Constant quality used when no bitrate is selected, the defaults recommended for each encoder
+    pub fn default_crf(&self) -> u8 {
+        match self {
+            VideoCodec::H264   => 23,
+            VideoCodec::H265   => 28,
+            VideoCodec::Vp9    => 31,
+            VideoCodec::Av1    => 30,
+            VideoCodec::SvtAv1 => 35,
+        }
+    }
+
+    /// Constant quality of the quality level, picked so that each level looks about the same with every codec
+    pub fn quality_crf(&self, quality: Quality) -> u8 {
+        let values = match self {
+            VideoCodec::H264   => [ 18, 23, 28, 33, 40 ],
+            VideoCodec::H265   => [ 20, 26, 30, 34, 40 ],
+            VideoCodec::Vp9    => [ 24, 31, 37, 44, 52 ],
+            VideoCodec::Av1    => [ 22, 30, 36, 44, 52 ],
+            VideoCodec::SvtAv1 => [ 24, 32, 38, 46, 54 ],
+        };
+        values[quality as usize]
+    }
+
+    /// Arguments of the constant quality mode
+    pub fn crf_args(&self, crf: u8) -> Vec<String> {
+        let mut args = vec![ "-crf".to_string(), crf.to_string() ];
+
+        // Without the zero bitrate libvpx and libaom use the crf only as the upper quality limit of the default bitrate
+        if matches!(self, VideoCodec::Vp9 | VideoCodec::Av1) {
+            args.extend([ "-b:v".to_string(), "0".to_string() ]);
+        }
+
+        args
+    }
+
+    /// ffmpeg can not run SVT-AV1 in two passes, its bitrate mode is used in a single pass instead
+    pub fn supports_two_pass(&self) -> bool {
+        !matches!(self, VideoCodec::SvtAv1)
+    }
+
+    /// Arguments of the pass (1 or 2) of the two-pass encoding. The first pass writes its statistics
+    /// to the files starting with the `log_file`, so the jobs running at the same time do not share them.
+    pub fn pass_args(&self, pass: u8, log_file: &Path) -> Vec<String> {
+        let log_file = log_file.to_string_lossy();
+        match self {
+            // libx265 ignores the -pass and -passlogfile options of ffmpeg. Its options are separated with
+            // colons, which a Windows path contains as well.
+            VideoCodec::H265 => {
+                let stats = log_file.replace('\\', "\\\\").replace(':', "\\:");
+                vec![ "-x265-params".into(), format!("pass={pass}:stats={stats}") ]
+            }
+            _ => vec![ "-pass".into(), pass.to_string(), "-passlogfile".into(), log_file.to_string() ],
+        }
+    }
+
+    /// Files the two-pass encoding writes with the `log_file` of pass_args. The encoders write into the
+    /// temporary files first, which are left behind when the pass is canceled.
+    pub fn pass_log_files(&self, log_file: &Path) -> Vec<PathBuf> {
+        // ffmpeg adds the index of the output stream to the name, the video is always the first stream
+        let (log, tree) = match self {
+            VideoCodec::H265 => (log_file.as_os_str().to_owned(), ".cutree"),
+            _ => {
+                let mut log = log_file.as_os_str().to_owned();
+                log.push("-0.log");
+                (log, ".mbtree")
+            }
+        };
+
+        [ "", ".temp", tree, &format!("{tree}.temp") ].iter().map(|suffix| {
+            let mut file = log.clone();
+            file.push(suffix);
+            PathBuf::from(file)
+        }).collect()
+    }
+}
+
+impl AudioCodec {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3    => "MP3",
+            AudioCodec::Aac    => "AAC (m4a)",
+            AudioCodec::Opus   => "Opus",
+            AudioCodec::Vorbis => "Vorbis (ogg)",
+            AudioCodec::Flac   => "FLAC",
+            AudioCodec::Wav    => "WAV",
+        }
+    }
+
+    /// Name used in the command line arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3    => "mp3",
+            AudioCodec::Aac    => "aac",
+            AudioCodec::Opus   => "opus",
+            AudioCodec::Vorbis => "vorbis",
+            AudioCodec::Flac   => "flac",
+            AudioCodec::Wav    => "wav",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        AUDIO_CODECS.iter().copied().find(|codec| codec.name() == name.to_lowercase())
+    }
+
+    /// Name of the ffmpeg encoder
+    pub fn encoder(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3    => "libmp3lame",
+            AudioCodec::Aac    => "aac",
+            AudioCodec::Opus   => "libopus",
+            AudioCodec::Vorbis => "libvorbis",
+            AudioCodec::Flac   => "flac",
+            AudioCodec::Wav    => "pcm_s16le",
+        }
+    }
+
+    /// Extension of the file the codec is stored in when there is no video
+    pub fn container(&self) -> &'static str {
+        match self {
+            AudioCodec::Mp3    => "mp3",
+            AudioCodec::Aac    => "m4a",
+            AudioCodec::Opus   => "opus",
+            AudioCodec::Vorbis => "ogg",
+            AudioCodec::Flac   => "flac",
+            AudioCodec::Wav    => "wav",
+        }
+    }
+
+    pub fn is_lossless(&self) -> bool {
+        matches!(self, AudioCodec::Flac | AudioCodec::Wav)
+    }
+
+    /// Bitrates (kbit/s) the encoder can be set to, None for the lossless codecs
+    pub fn bitrate_range(&self) -> Option<RangeInclusive<u32>> {
+        match self {
+            AudioCodec::Mp3    => Some(MP3_BITRATE_RANGE),
+            AudioCodec::Aac    => Some(AAC_BITRATE_RANGE),
+            AudioCodec::Opus   => Some(OPUS_BITRATE_RANGE),
+            AudioCodec::Vorbis => Some(VORBIS_BITRATE_RANGE),
+            AudioCodec::Flac | AudioCodec::Wav => None,
+        }
+    }
+
+    pub fn supports_sample_rate(&self, sample_rate: u32) -> bool {
+        match self {
+            AudioCodec::Opus => OPUS_SAMPLE_RATES.contains(&sample_rate),
+            AudioCodec::Mp3  => MP3_SAMPLE_RATES.contains(&sample_rate),
+            _ => true,
+        }
+    }
+
+    /// Error shown when the sample rate is not supported by the codec
+    pub fn sample_rate_error(&self) -> &'static str {
+        match self {
+            AudioCodec::Opus => "Opus audio only supports 8000, 12000, 16000, 24000 and 48000 Hz sample rates.",
+            AudioCodec::Mp3  => "MP3 audio only supports sample rates between 8000 and 48000 Hz.",
+            _ => "Selected audio sample rate is not supported.",
+        }
+    }
+
+    /// VBR level (-q:a) of the quality for LAME and libvorbis. The other lossy encoders get a bitrate
+    /// relative to the source instead (see ConvertionJob::quality_bitrates).
+    pub fn vbr_level(&self, quality: Quality) -> Option<u8> {
+        let index = quality as usize;
+
+        match self {
+            AudioCodec::Mp3    => Some([ 0, 3, 5, 7, 9 ][index]),
+            AudioCodec::Vorbis => Some([ 8, 6, 4, 2, 0 ][index]),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Cropping of the black bars (letterboxing and pillarboxing). The bars are found by the cropdetect filter
+//! of ffmpeg run over a few short parts spread over the media, which is much faster than decoding all of it.
+
+use std::process::{Command, Stdio};
+
+use crate::MediaInfo;
+use crate::media_info::StreamInfo;
+use super::frames::thumbnail_times;
+use super::runner::{read_log, ProcessRunner};
+use super::trim::{format_seconds, Trim};
+
+/// Number of the parts of the media the black bars are detected in
+pub const CROP_SAMPLES: usize = 6;
+/// Length of a single part in ms
+const SAMPLE_DURATION: u64 = 2000;
+
+/// Part of the picture kept by the crop, in px of the displayed (rotated) picture
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct CropRect {
+    pub width: u32,
+    pub height: u32,
+    /// Offset of the kept part from the left edge
+    pub x: u32,
+    /// Offset of the kept part from the top edge
+    pub y: u32,
+}
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum Crop {
+    #[default]
+    None,
+    /// The black bars are detected right before the convertion
+    Detect,
+    /// Already detected (or selected) part of the picture
+    Rect(CropRect),
+}
+
+impl CropRect {
+    pub fn filter(&self) -> String {
+        format!("crop={}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+
+    /// Set when nothing is cut off the `width` x `height` picture
+    pub fn is_full(&self, width: u32, height: u32) -> bool {
+        self.width >= width && self.height >= height
+    }
+
+    pub fn fits(&self, width: u32, height: u32) -> bool {
+        self.width > 0 && self.height > 0 && self.x + self.width <= width && self.y + self.height <= height
+    }
+
+    /// The smallest rectangle containing both of the rectangles
+    pub fn union(&self, other: &CropRect) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        let right = (self.x + self.width).max(other.x + other.width);
+        let bottom = (self.y + self.height).max(other.y + other.height);
+        Self { width: right - x, height: bottom - y, x, y }
+    }
+
+    /// The `video` stream as it is after the crop, used to scale the cropped picture
+    pub fn apply(&self, video: &StreamInfo) -> StreamInfo {
+        StreamInfo { width: Some(self.width), height: Some(self.height), rotation: 0, ..video.clone() }
+    }
+
+    /// Reads the crop from the log of the cropdetect filter. With reset=0 the filter keeps growing the
+    /// rectangle until it covers everything that is not black, so the last line holds the stable one.
+    /// Returns None when the whole part was black (the printed size is negative then).
+    pub fn from_cropdetect_output(output: &str) -> Option<Self> {
+        let line = output.lines().rev().find(|line| line.contains("Parsed_cropdetect"))?;
+        let values = line.rsplit_once("crop=")?.1.split_whitespace().next()?;
+
+        let values: Vec<i64> = values.split(':').map(str::parse).collect::<Result<_, _>>().ok()?;
+        let [width, height, x, y] = values[..] else {
+            return None;
+        };
+
+        if width <= 0 || height <= 0 || x < 0 || y < 0 {
+            return None;
+        }
+        Some(Self { width: width as u32, height: height as u32, x: x as u32, y: y as u32 })
+    }
+}
+
+/// Process detecting the black bars in the part of the media starting at `time` and lasting `duration` (in ms).
+/// The detected crops are printed to the standard error.
+pub fn cropdetect_command(ffmpeg: &str, input_file: &str, time: u64, duration: u64) -> Command {
+    let mut process = Command::new(ffmpeg);
+    process.args([ "-nostdin", "-hide_banner", "-nostats" ]);
+    process.args([ "-ss", &format_seconds(time), "-t", &format_seconds(duration), "-i", input_file ]);
+    // round=2 keeps both dimensions even, as the encoders need them
+    process.args([ "-an", "-sn", "-vf", "cropdetect=limit=24:round=2:reset=0", "-f", "null", "-" ]);
+    process.stderr(Stdio::piped());
+    process
+}
+
+/// Detects the black bars in the parts spread over the trimmed part of the media. A bar has to be black in
+/// all of the parts, so a dark scene never gets the picture cut off. Returns None when the media has no video
+/// or when ffmpeg fails, the returned rectangle may cover the whole picture when there are no bars.
+pub fn detect_crop(runner: &dyn ProcessRunner, input_file: &str, media_info: &MediaInfo, trim: &Trim) -> Option<CropRect> {
+    let ffmpeg = runner.find_program("ffmpeg")?;
+    media_info.first_video()?;
+
+    let duration = media_info.duration().unwrap_or(0);
+    let start = trim.start.unwrap_or(0);
+    let length = trim.trimmed_duration(duration).unwrap_or(0);
+
+    // Without the duration only the beginning of the media is checked
+    let (times, sample_duration) = if length == 0 {
+        (vec![ start ], SAMPLE_DURATION * CROP_SAMPLES as u64)
+    } else {
+        let sample_duration = SAMPLE_DURATION.min(length / CROP_SAMPLES as u64).max(1);
+        let times = thumbnail_times(length, CROP_SAMPLES).into_iter().map(|time| start + time).collect();
+        (times, sample_duration)
+    };
+
+    let mut crop: Option<CropRect> = None;
+    for time in times {
+        let log = read_log(runner, cropdetect_command(&ffmpeg, input_file, time, sample_duration))?;
+        if let Some(rect) = CropRect::from_cropdetect_output(&log) {
+            crop = Some(crop.map_or(rect, |crop| crop.union(&rect)));
+        }
+    }
+
+    // Every part was black, there is nothing to tell the bars from
+    if crop.is_none() {
+        let (width, height) = media_info.first_video()?.display_size()?;
+        return Some(CropRect { width, height, x: 0, y: 0 });
+    }
+    crop
+}
@@ -0,0 +1,115 @@
+//! Frame rate of the Video mode. The frame rate can be capped (the high frame rate screen recordings waste
+//! a lot of the bitrate) or converted, and the variable frame rate of the screen and phone recordings can be
+//! turned into a constant one, which keeps the audio in sync in the players and editors that expect it.
+
+use crate::media_info::StreamInfo;
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum FrameRateTarget {
+    #[default]
+    Keep,
+    /// Lowers the frame rate when the source is faster, the slower sources keep their frame rate
+    Cap(f64),
+    /// Changes the frame rate to exactly this value, dropping or duplicating the frames
+    Convert(f64),
+}
+
+/// How the frames are removed when the frame rate gets lower
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum FrameRateMethod {
+    /// The fps filter, the frames of the output are evenly spaced
+    #[default]
+    Filter,
+    /// The frames coming too early are dropped, the timing of the kept frames does not change
+    Drop,
+}
+
+pub const FRAME_RATE_METHODS: &[FrameRateMethod] = &[ FrameRateMethod::Filter, FrameRateMethod::Drop ];
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub struct FrameRate {
+    pub target: FrameRateTarget,
+    pub method: FrameRateMethod,
+    /// Every frame of the output gets the same duration (CFR), the frames are duplicated or dropped for it
+    pub constant: bool,
+}
+
+impl FrameRateTarget {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrameRateTarget::Keep       => "Keep",
+            FrameRateTarget::Cap(_)     => "Cap at",
+            FrameRateTarget::Convert(_) => "Convert to",
+        }
+    }
+}
+
+impl FrameRateMethod {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            FrameRateMethod::Filter => "fps filter",
+            FrameRateMethod::Drop   => "Drop frames",
+        }
+    }
+}
+
+impl FrameRate {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match self.target {
+            FrameRateTarget::Cap(fps) | FrameRateTarget::Convert(fps) if !(1.0..=240.0).contains(&fps) => {
+                Err("Frame rate must be between 1 and 240 fps.")
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Frame rate of the output, None when the frame rate of the source is kept
+    pub fn output_rate(&self, source_fps: Option<f64>) -> Option<f64> {
+        match self.target {
+            FrameRateTarget::Keep => None,
+            // The average frame rate of a 60 fps recording is often a tiny bit above 60
+            FrameRateTarget::Cap(fps) if source_fps.is_some_and(|source_fps| source_fps <= fps + 0.01) => None,
+            FrameRateTarget::Cap(fps) | FrameRateTarget::Convert(fps) => Some(fps),
+        }
+    }
+
+    /// Video filter changing the frame rate, only used by the FrameRateMethod::Filter
+    pub fn filter(&self, source_fps: Option<f64>) -> Option<String> {
+        if self.method != FrameRateMethod::Filter {
+            return None;
+        }
+        self.output_rate(source_fps).map(|fps| format!("fps={}", format_rate(fps)))
+    }
+
+    /// Output arguments of the frame rate, used together with the filter
+    pub fn output_args(&self, source_fps: Option<f64>) -> Vec<String> {
+        match (self.method, self.output_rate(source_fps), source_fps) {
+            // The variable frame rate output only drops the frames, it never duplicates them
+            (FrameRateMethod::Drop, Some(fps), _) => {
+                let mode = if self.constant { "cfr" } else { "vfr" };
+                vec![ "-r".into(), format_rate(fps), "-fps_mode".into(), mode.into() ]
+            }
+            _ if !self.constant => Vec::new(),
+            // Without the selected frame rate ffmpeg would guess the constant one from the timestamps, which
+            // for the variable frame rate sources is often far too high. The average frame rate is used instead.
+            (_, None, Some(source_fps)) => {
+                vec![ "-r".into(), format_rate(source_fps), "-fps_mode".into(), "cfr".into() ]
+            }
+            _ => vec![ "-fps_mode".into(), "cfr".into() ],
+        }
+    }
+}
+
+/// Frame rate of the video stream as shown to the user, for example "59.94 fps (variable)"
+pub fn describe_frame_rate(video: &StreamInfo) -> Option<String> {
+    let fps = video.frame_rate()?;
+    let variable = if video.is_variable_frame_rate() { " (variable)" } else { "" };
+    Some(format!("{} fps{variable}", format_rate(fps)))
+}
+
+/// Frame rate with up to 3 decimal places, for example "30" or "29.97"
+fn format_rate(fps: f64) -> String {
+    format!("{fps:.3}").trim_end_matches('0').trim_end_matches('.').to_string()
+}
@@ -0,0 +1,58 @@
+//! Single frames of the video decoded by ffmpeg into raw RGBA pixels, used for the previews in the GUI.
+
+use std::process::Command;
+
+use crate::MediaInfo;
+use super::runner::{read_output_bytes, ProcessRunner};
+
+pub struct Frame {
+    /// Position of the frame in the media in ms
+    pub time: u64,
+    pub width: usize,
+    pub height: usize,
+    /// Pixels row by row, 4 bytes each
+    pub rgba: Vec<u8>,
+}
+
+/// Size of the video frames scaled to the `height`, keeping the aspect ratio of the video
+pub fn frame_size(media_info: &MediaInfo, height: usize) -> Option<(usize, usize)> {
+    // ffmpeg rotates the frames before they are scaled, so the frame has the size of the displayed picture
+    let (video_width, video_height) = media_info.first_video()?.display_size()?;
+    let (video_width, video_height) = (video_width as usize, video_height as usize);
+    if video_width == 0 || video_height == 0 || height == 0 {
+        return None;
+    }
+
+    let width = (video_width * height / video_height).max(2);
+    Some((width - width % 2, height))
+}
+
+/// Decodes the frame at the `time` (in ms) scaled to the `height`. Returns None when the media has no
+/// video or when ffmpeg fails.
+pub fn extract_frame(
+    runner: &dyn ProcessRunner, input_file: &str, media_info: &MediaInfo, time: u64, height: usize,
+) -> Option<Frame> {
+    let ffmpeg = runner.find_program("ffmpeg")?;
+    let (width, height) = frame_size(media_info, height)?;
+
+    let mut process = Command::new(ffmpeg);
+    process.args([ "-nostdin", "-v", "error" ]);
+    // Seeking in the input decodes only from the closest keyframe, so the frame is found quickly
+    process.args([ "-ss", &format!("{}.{:03}", time / 1000, time % 1000) ]);
+    process.args([ "-i", input_file ]);
+    process.args([ "-frames:v", "1", "-vf", &format!("scale={width}:{height}") ]);
+    process.args([ "-f", "rawvideo", "-pix_fmt", "rgba", "pipe:1" ]);
+
+    let rgba = read_output_bytes(runner, process)?;
+    if rgba.len() != width * height * 4 {
+        return None;
+    }
+
+    Some(Frame { time, width, height, rgba })
+}
+
+/// Positions (in ms) of the `count` thumbnails evenly spread over the media, each one in the middle of its part
+pub fn thumbnail_times(duration: u64, count: usize) -> Vec<u64> {
+    let count = count as u64;
+    (0..count).map(|i| duration * (2 * i + 1) / (2 * count)).collect()
+}
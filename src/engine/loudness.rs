@@ -0,0 +1,102 @@
+//! Loudness normalization (EBU R128) with the loudnorm filter of ffmpeg. The filter is run twice: the
+//! first pass only measures the audio, the second one applies the measured values, which lets the filter
+//! use the linear normalization and keep the dynamics of the audio.
+
+use std::ops::RangeInclusive;
+
+use serde::Deserialize;
+
+/// Loudness the audio is brought to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Loudnorm {
+    /// Integrated loudness in LUFS
+    pub integrated: f32,
+    /// Maximum true peak in dBTP
+    pub true_peak: f32,
+    /// Loudness range in LU
+    pub range: f32,
+}
+
+impl Default for Loudnorm {
+    /// Values recommended by EBU R128
+    fn default() -> Self {
+        Self { integrated: -23.0, true_peak: -1.0, range: 7.0 }
+    }
+}
+
+// Values accepted by the loudnorm filter
+pub const INTEGRATED_RANGE: RangeInclusive<f32> = -70.0..=-5.0;
+pub const TRUE_PEAK_RANGE:  RangeInclusive<f32> = -9.0..=0.0;
+pub const LOUDNESS_RANGE:   RangeInclusive<f32> = 1.0..=20.0;
+
+/// Loudness of the input measured by the first pass
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoudnessMeasurement {
+    pub integrated: f32,
+    pub true_peak: f32,
+    pub range: f32,
+    pub threshold: f32,
+    pub target_offset: f32,
+}
+
+/// Summary printed by the loudnorm filter with print_format=json, all of the values are strings
+#[derive(Deserialize)]
+struct LoudnormOutput {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+impl Loudnorm {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if !INTEGRATED_RANGE.contains(&self.integrated) {
+            return Err("Target loudness must be between -70 and -5 LUFS.");
+        }
+        if !TRUE_PEAK_RANGE.contains(&self.true_peak) {
+            return Err("Target true peak must be between -9 and 0 dBTP.");
+        }
+        if !LOUDNESS_RANGE.contains(&self.range) {
+            return Err("Target loudness range must be between 1 and 20 LU.");
+        }
+        Ok(())
+    }
+
+    fn targets(&self) -> String {
+        format!("I={:.1}:TP={:.1}:LRA={:.1}", self.integrated, self.true_peak, self.range)
+    }
+
+    /// Audio filter of the first pass, it prints the measured values to the standard error
+    pub fn analysis_filter(&self) -> String {
+        format!("loudnorm={}:print_format=json", self.targets())
+    }
+
+    /// Audio filter of the second pass. loudnorm always outputs 192 kHz audio, so it is resampled back
+    /// to the `sample_rate` of the source.
+    pub fn filter(&self, measured: &LoudnessMeasurement, sample_rate: u32) -> String {
+        format!(
+            "loudnorm={}:measured_I={:.2}:measured_TP={:.2}:measured_LRA={:.2}:measured_thresh={:.2}:offset={:.2}:linear=true,aresample={sample_rate}",
+            self.targets(), measured.integrated, measured.true_peak, measured.range, measured.threshold, measured.target_offset,
+        )
+    }
+}
+
+impl LoudnessMeasurement {
+    /// Reads the values from the log of the first pass, the JSON summary is the last thing printed there.
+    /// Returns None when the summary is missing or the audio is silent (the values are infinite then).
+    pub fn from_ffmpeg_output(output: &str) -> Option<Self> {
+        let start = output.rfind('{')?;
+        let end = start + output[start..].find('}')? + 1;
+        let summary: LoudnormOutput = serde_json::from_str(&output[start..end]).ok()?;
+
+        let parse = |value: &str| value.trim().parse::<f32>().ok().filter(|value| value.is_finite());
+        Some(Self {
+            integrated: parse(&summary.input_i)?,
+            true_peak: parse(&summary.input_tp)?,
+            range: parse(&summary.input_lra)?,
+            threshold: parse(&summary.input_thresh)?,
+            target_offset: parse(&summary.target_offset)?,
+        })
+    }
+}
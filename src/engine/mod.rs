@@ -0,0 +1,642 @@
+//! The encoding engine, independent of the GUI. Jobs are described with plain structs, started with
+//! `CompressorJob::start` and report their progress and the result through the returned `JobHandle`.
+
+use std::{
+    sync::{Arc, atomic::{AtomicU64, Ordering}, mpsc::{Receiver, Sender, RecvTimeoutError}}, path::{Path, PathBuf}, time::Duration,
+};
+
+use crate::progress::{format_duration, format_size};
+use animation::AnimationOptions;
+use codec::{AudioCodec, VideoCodec};
+use crop::Crop;
+use framerate::FrameRate;
+use loudness::Loudnorm;
+use scale::Scaling;
+use runner::{ProcessRunner, SystemRunner};
+use subtitles::Subtitles;
+use thumbnail::ThumbnailOptions;
+use trim::Trim;
+
+pub mod animation;
+pub mod batch;
+pub mod bitrate;
+pub mod codec;
+pub mod crop;
+pub mod framerate;
+pub mod frames;
+pub mod loudness;
+mod processes;
+pub mod runner;
+pub mod scale;
+pub mod subtitles;
+pub mod thumbnail;
+pub mod trim;
+
+pub const PRESET_FFMPEG_STRINGS:     &[&str] = &[ "veryslow", "slow", "medium", "fast", "ultrafast", ];
+/// Resolutions selectable in the GUI, the length of the short side of the video
+pub const RESOLUTIONS:               &[u32]  = &[ 1080, 720, 480, 360, 144, ];
+
+// Values accepted by ffmpeg for the options of the Advanced mode
+pub const ADVANCED_PRESET_STRINGS: &[&str] = &[
+    "ultrafast", "superfast", "veryfast", "faster", "fast", "medium", "slow", "slower", "veryslow", "placebo",
+];
+pub const ADVANCED_TUNE_STRINGS:   &[&str] = &[ "", "film", "animation", "grain", "stillimage", "fastdecode", "zerolatency", ];
+pub const AUDIO_SAMPLE_RATES:      &[u32]  = &[ 8000, 11025, 12000, 16000, 22050, 24000, 32000, 44100, 48000, 88200, 96000, ];
+
+/// Quality levels, the bitrates they select are a fraction of the bitrates of the source streams
+#[derive(Default, Copy, Clone)]
+pub enum Quality {
+    #[default]
+    // 100%
+    Original = 0,
+    // 90%
+    Good     = 1,
+    // 60%
+    Medium   = 2,
+    // 30%
+    Bad      = 3,
+    // 10%
+    Poop     = 4,
+}
+
+/// How the video quality levels are encoded
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum VideoRateMode {
+    /// Each quality level is a constant quality (CRF) value, the size of the output depends on the content
+    #[default]
+    QualityFirst,
+    /// Each quality level is a fixed bitrate, the quality of the output depends on the content
+    BitrateFirst,
+}
+
+impl VideoRateMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            VideoRateMode::QualityFirst => "Quality first",
+            VideoRateMode::BitrateFirst => "Bitrate first",
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Default, Copy, Clone)]
+pub enum AppMode {
+    #[default]
+    Audio    = 0,
+    Video    = 1,
+    // Audio and Video quality selection option for download (or maybe don't to this and just reencode?)
+    Download = 2,
+    // When advanced is selected you can still select either audio or video but the selections the
+    // quality options are greatly expanded (audio bitrade/sample rate, video bitrate (actual kB/s values in the selections))
+    Advanced = 3,
+    /// A single frame or a contact sheet of the video saved as an image
+    Thumbnail = 4,
+    /// Part of the video saved as an animated GIF or WebP
+    Animation = 5,
+    // Download mode is always selected, but you can also select either audio or video option
+    // DualMode = 6,
+}
+
+impl AppMode {
+    /// Whether the output can be brought to the selected size, the images of the Thumbnail mode for
+    /// example can not get any smaller
+    pub fn uses_target_size(&self) -> bool {
+        matches!(self, AppMode::Audio | AppMode::Video | AppMode::Animation)
+    }
+}
+
+/// Progress of the job, sent from the working thread
+#[derive(Default, Clone)]
+pub struct WorkProgress {
+    /// Value between 0.0 and 1.0
+    pub fraction: f32,
+    /// Estimated time left until the job is done
+    pub eta: Option<Duration>,
+    /// Encoding speed relative to the playback speed
+    pub speed: Option<f32>,
+    /// Frames encoded per second
+    pub fps: Option<f32>,
+    /// Download speed in bytes per second
+    pub download_speed: Option<f64>,
+    /// Name of the file currently being downloaded
+    pub current_file: Option<String>,
+    /// Attempt of the target-size convertion (starting from 1), set when the output size is verified
+    pub size_attempt: Option<u32>,
+}
+
+/// Options of the Advanced mode. Zero values keep the values of the input file.
+#[derive(Clone)]
+pub struct ConvertOptions {
+    pub audio_enabled: bool,
+    /// Audio bitrate in kbit/s
+    pub audio_bitrate: u32,
+    /// Audio sample rate in Hz
+    pub audio_sample_rate: u32,
+
+    pub video_enabled: bool,
+    /// Video bitrate in kbit/s, the constant quality (CRF) is used when set to zero
+    pub video_bitrate: u32,
+    /// When only one of the dimensions is set, the other one keeps the aspect ratio
+    pub video_width: u32,
+    pub video_height: u32,
+
+    /// One of the ADVANCED_PRESET_STRINGS
+    pub preset: &'static str,
+    /// One of the ADVANCED_TUNE_STRINGS, empty when not used
+    pub tune: &'static str,
+    /// Constant quality of the video, lower is better (0 - lossless, up to VideoCodec::max_crf - worst)
+    pub crf: u8,
+}
+
+impl Quality {
+    pub fn from_usize(num: usize) -> Self {
+        match num {
+            0 => Quality::Original,
+            1 => Quality::Good,
+            2 => Quality::Medium,
+            3 => Quality::Bad,
+            4 => Quality::Poop,
+            _ => panic!("Incorrect size"),
+        }
+    }
+
+    /// Fraction of the source bitrate kept at this quality
+    pub fn bitrate_fraction(&self) -> f32 {
+        match self {
+            Quality::Original => 1.0,
+            Quality::Good     => 0.9,
+            Quality::Medium   => 0.6,
+            Quality::Bad      => 0.3,
+            Quality::Poop     => 0.1,
+        }
+    }
+
+    /// Sample rate (in Hz) the audio is reduced to at this quality, None keeps the original one
+    pub fn sample_rate(&self) -> Option<u32> {
+        match self {
+            Quality::Original => None,
+            Quality::Good     => Some(32000),
+            Quality::Medium   => Some(24000),
+            Quality::Bad      => Some(16000),
+            Quality::Poop     => Some(8000),
+        }
+    }
+}
+
+impl ConvertOptions {
+    /// Checks whether all of the options are in the ranges accepted by ffmpeg and the selected codecs.
+    /// The `audio_codec` is used only when the video is disabled, otherwise the audio codec is picked
+    /// by the container of the video codec.
+    pub fn validate(&self, codec: VideoCodec, audio_codec: AudioCodec) -> Result<(), &'static str> {
+        if !self.audio_enabled && !self.video_enabled {
+            return Err("Both audio and video are disabled, there is nothing to convert.");
+        }
+
+        if self.audio_enabled {
+            if self.audio_bitrate != 0 && !(8..=640).contains(&self.audio_bitrate) {
+                return Err("Audio bitrate must be between 8 and 640 kbit/s.");
+            }
+
+            if self.audio_sample_rate != 0 && !AUDIO_SAMPLE_RATES.contains(&self.audio_sample_rate) {
+                return Err("Selected audio sample rate is not supported.");
+            }
+
+            let audio_codec = if self.video_enabled { codec.audio_codec() } else { audio_codec };
+            if self.audio_sample_rate != 0 && !audio_codec.supports_sample_rate(self.audio_sample_rate) {
+                return Err(audio_codec.sample_rate_error());
+            }
+
+            if self.audio_bitrate != 0 && audio_codec.is_lossless() {
+                return Err("Lossless audio codecs can not be set to a bitrate.");
+            }
+        }
+
+        if self.video_enabled {
+            if self.video_bitrate != 0 && !(16..=200_000).contains(&self.video_bitrate) {
+                return Err("Video bitrate must be between 16 and 200000 kbit/s.");
+            }
+
+            // libx264 only accepts even dimensions
+            for size in [self.video_width, self.video_height] {
+                if size != 0 && (!(16..=8192).contains(&size) || size % 2 != 0) {
+                    return Err("Video width and height must be even numbers between 16 and 8192.");
+                }
+            }
+
+            if self.crf > codec.max_crf() {
+                return Err("CRF is out of the range supported by the selected codec.");
+            }
+
+            if !ADVANCED_PRESET_STRINGS.contains(&self.preset) {
+                return Err("Selected preset is not supported.");
+            }
+
+            if !ADVANCED_TUNE_STRINGS.contains(&self.tune) {
+                return Err("Selected tune is not supported.");
+            }
+
+            if !codec.supports_tune(self.tune) {
+                return Err("Selected tune is not supported by the selected codec.");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for ConvertOptions {
+    fn default() -> Self {
+        Self {
+            audio_enabled: true,
+            audio_bitrate: 0,
+            audio_sample_rate: 0,
+
+            video_enabled: true,
+            video_bitrate: 0,
+            video_width: 0,
+            video_height: 0,
+
+            preset: "medium",
+            tune: "",
+            // Default of libx264
+            crf: 23,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DownloadJob {
+    pub input_link: String,
+    /// Can be empty, yt-dlp then picks the file name on its own
+    pub output_file: String
+}
+
+/// Snapshot of the options selected at the time the job was created
+#[derive(Clone)]
+pub struct ConvertionJob {
+    pub input_file: String,
+    pub output_file: String,
+
+    pub mode: AppMode,
+    pub audio_quality: Quality,
+    pub video_quality: Quality,
+    /// Whether the video_quality selects the constant quality or the bitrate
+    pub video_rate_mode: VideoRateMode,
+
+    /// Size the video is scaled to in the Video mode, None keeps the original resolution
+    pub scaling: Option<Scaling>,
+    /// Black bars cropped off the video in the Video mode, before the scaling
+    pub crop: Crop,
+    /// Frame rate of the video in the Video mode
+    pub frame_rate: FrameRate,
+    /// Index into PRESET_FFMPEG_STRINGS
+    pub preset: usize,
+    /// Used in the Video and Advanced modes
+    pub video_codec: VideoCodec,
+    /// Used in the Audio mode and in the Advanced mode without the video
+    pub audio_codec: AudioCodec,
+    /// FLAC compression level (0 - fastest, 12 - smallest file), the quality is the same for all of them
+    pub flac_compression: u8,
+
+    /// Approximate output file size in MB, None when the quality options should be used instead
+    pub output_file_size: Option<f32>,
+    /// Video to audio bitrate ratio used to split the bitrate when the output_file_size is set
+    pub bitrate_ratio: f32,
+    /// Measure the output and encode it again with a lower bitrate when it exceeds the output_file_size
+    pub verify_output_size: bool,
+
+    /// Part of the media kept in the output
+    pub trim: Trim,
+    /// Loudness the audio is normalized to in the Audio and Video modes, None keeps the original volume
+    pub loudnorm: Option<Loudnorm>,
+    /// Subtitles of the Video mode
+    pub subtitles: Subtitles,
+
+    /// Used only in the Advanced mode
+    pub advanced_options: ConvertOptions,
+    /// Used only in the Thumbnail mode
+    pub thumbnail_options: ThumbnailOptions,
+    /// Used only in the Animation mode. With the output_file_size set, the frame rate and the width are
+    /// lowered until the animation fits.
+    pub animation_options: AnimationOptions,
+}
+
+/// Downloads the media into a temporary directory and converts it right after
+#[derive(Clone)]
+pub struct DualJob {
+    pub input_link: String,
+    /// The input file is filled in once the download is done. The output file is empty when
+    /// it should be created from the name of the downloaded file.
+    pub convertion: ConvertionJob,
+    /// Directory of the output created from the name of the downloaded file
+    pub output_dir: PathBuf,
+}
+
+#[derive(Clone)]
+pub enum CompressorJob {
+    Convert(Box<ConvertionJob>),
+    Download(Box<DownloadJob>),
+    Dual(Box<DualJob>),
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Canceled,
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MessageKind {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// Message describing the result of the job, meant to be shown to the user
+#[derive(Clone, Debug)]
+pub struct MessageLog {
+    pub text: &'static str,
+    pub kind: MessageKind,
+}
+
+/// Everything reported by the working thread of a job. `Finished` is always the last event.
+#[derive(Clone)]
+pub enum JobEvent {
+    Progress(WorkProgress),
+    Message(MessageLog),
+    Finished(JobStatus),
+}
+
+/// Handle to a started job. Dropping the handle does not stop the job.
+pub struct JobHandle {
+    events: Receiver<JobEvent>,
+    abort: Sender<()>,
+}
+
+impl JobHandle {
+    /// Returns all of the events received since the last call, without blocking.
+    pub fn poll(&self) -> impl Iterator<Item = JobEvent> + '_ {
+        self.events.try_iter()
+    }
+
+    /// Waits for the next event. Returns None on timeout, or when the job thread stopped without
+    /// reporting the result (this is turned into the `Finished(JobStatus::Failed)` event).
+    pub fn wait_event(&self, timeout: Duration) -> Option<JobEvent> {
+        match self.events.recv_timeout(timeout) {
+            Ok(event) => Some(event),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(JobEvent::Finished(JobStatus::Failed)),
+        }
+    }
+
+    /// Asks the job to stop. The job reports `JobStatus::Canceled` once it actually stopped.
+    pub fn cancel(&self) {
+        let _ = self.abort.send(());
+    }
+}
+
+/// Used by the working threads to report the progress and the result of the job
+struct ThreadChannels {
+    event_tx: Sender<JobEvent>,
+    abort_rx: Receiver<()>,
+    /// Starts all of the processes of the job
+    runner: Arc<dyn ProcessRunner>,
+}
+
+impl ThreadChannels {
+    fn new(runner: Arc<dyn ProcessRunner>) -> (Self, JobHandle) {
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let (abort_tx, abort_rx) = std::sync::mpsc::channel();
+
+        let channels = Self { event_tx, abort_rx, runner };
+        let handle = JobHandle { events: event_rx, abort: abort_tx };
+        (channels, handle)
+    }
+
+    fn is_canceled(&self) -> bool {
+        self.abort_rx.try_recv().is_ok()
+    }
+
+    fn send_progress(&self, progress: WorkProgress) {
+        let _ = self.event_tx.send(JobEvent::Progress(progress));
+    }
+
+    fn send_finished(&self, message: &'static str, kind: MessageKind, status: JobStatus) {
+        let _ = self.event_tx.send(JobEvent::Message(MessageLog { text: message, kind }));
+        let _ = self.event_tx.send(JobEvent::Finished(status));
+    }
+
+    fn send_error(&self, message: &'static str) {
+        self.send_finished(message, MessageKind::Error, JobStatus::Failed);
+    }
+
+    fn send_success(&self, message: &'static str) {
+        self.send_finished(message, MessageKind::Success, JobStatus::Finished);
+    }
+
+    /// The job is done, but not everything went as expected
+    fn send_warning(&self, message: &'static str) {
+        self.send_finished(message, MessageKind::Warning, JobStatus::Finished);
+    }
+
+    fn send_canceled(&self, message: &'static str) {
+        self.send_finished(message, MessageKind::Info, JobStatus::Canceled);
+    }
+}
+
+impl JobStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JobStatus::Queued   => "Queued",
+            JobStatus::Running  => "Running",
+            JobStatus::Finished => "Finished",
+            JobStatus::Failed   => "Failed",
+            JobStatus::Canceled => "Canceled",
+        }
+    }
+}
+
+impl WorkProgress {
+    /// Speed and the estimated time left, for example: "1.50x, 45 fps, ETA 00:01:23"
+    pub fn details(&self) -> String {
+        let mut details = Vec::new();
+
+        if let Some(current_file) = &self.current_file {
+            // Only the file name, yt-dlp reports the whole output path
+            let file_name = Path::new(current_file).file_name().unwrap_or_default();
+            details.push(file_name.to_string_lossy().to_string());
+        }
+
+        if let Some(download_speed) = self.download_speed {
+            details.push(format!("{}/s", format_size(download_speed)));
+        }
+
+        if let Some(speed) = self.speed {
+            details.push(format!("{speed:.2}x"));
+        }
+
+        if let Some(fps) = self.fps.filter(|fps| *fps > 0.0) {
+            details.push(format!("{fps:.0} fps"));
+        }
+
+        if let Some(eta) = self.eta {
+            details.push(format!("ETA {}", format_duration(eta)));
+        }
+
+        if let Some(attempt) = self.size_attempt {
+            details.push(format!("size attempt {attempt}/{}", bitrate::MAX_SIZE_ATTEMPTS));
+        }
+
+        details.join(", ")
+    }
+}
+
+impl CompressorJob {
+    /// Starts the job on a new thread.
+    pub fn start(&self) -> JobHandle {
+        self.start_with(Arc::new(SystemRunner))
+    }
+
+    /// Starts the job on a new thread, with all of its processes started by the provided runner.
+    pub fn start_with(&self, runner: Arc<dyn ProcessRunner>) -> JobHandle {
+        let (channels, handle) = ThreadChannels::new(runner);
+
+        match self {
+            CompressorJob::Download(job) => job.download_resource(channels),
+            CompressorJob::Dual(job)     => job.download_and_convert(channels),
+            CompressorJob::Convert(job)  => job.compress(channels),
+        }
+
+        handle
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CompressorJob::Convert(_)  => "Convert",
+            CompressorJob::Download(_) => "Download",
+            CompressorJob::Dual(_)     => "Download + Convert",
+        }
+    }
+
+    pub fn input(&self) -> &str {
+        match self {
+            CompressorJob::Convert(job)  => &job.input_file,
+            CompressorJob::Download(job) => &job.input_link,
+            CompressorJob::Dual(job)     => &job.input_link,
+        }
+    }
+
+    pub fn output(&self) -> &str {
+        match self {
+            CompressorJob::Convert(job)  => &job.output_file,
+            CompressorJob::Download(job) => &job.output_file,
+            CompressorJob::Dual(job)     => &job.convertion.output_file,
+        }
+    }
+}
+
+impl ConvertionJob {
+    /// The fast cuts copy the streams, so they keep the extension of the input file. The images and the
+    /// animations are always encoded.
+    pub fn output_extension(&self) -> String {
+        if self.trim.is_stream_copy() && !matches!(self.mode, AppMode::Thumbnail | AppMode::Animation) {
+            if let Some(extension) = Path::new(&self.input_file).extension() {
+                return extension.to_string_lossy().to_lowercase();
+            }
+        }
+
+        let extension = output_extension(
+            self.mode, self.video_codec, self.audio_codec, &self.advanced_options, &self.thumbnail_options,
+            &self.animation_options,
+        );
+        String::from(extension.unwrap_or("mp4"))
+    }
+}
+
+/// Extension of the converted file, None when nothing is converted in the selected mode
+pub fn output_extension(
+    mode: AppMode, video_codec: VideoCodec, audio_codec: AudioCodec, advanced_options: &ConvertOptions,
+    thumbnail_options: &ThumbnailOptions, animation_options: &AnimationOptions,
+) -> Option<&'static str> {
+    match mode {
+        AppMode::Audio    => Some(audio_codec.container()),
+        AppMode::Video    => Some(video_codec.container()),
+        AppMode::Advanced if advanced_options.video_enabled => Some(video_codec.container()),
+        AppMode::Advanced => Some(audio_codec.container()),
+        AppMode::Thumbnail => Some(thumbnail_options.format.extension()),
+        AppMode::Animation => Some(animation_options.format.extension()),
+        AppMode::Download => None,
+    }
+}
+
+/// Creates "<input>-new.<ext>" path next to the input file.
+pub fn output_from_input_path(input_path: &str, extension: &str) -> Option<String> {
+    let input_path = Path::new(input_path);
+    if !input_path.exists() || input_path.is_dir() {
+        return None;
+    }
+
+    let input_filename = input_path.file_stem()?.to_str()?;
+    let mut output = PathBuf::from(input_path.parent()?);
+    output.push(format!("{input_filename}-new.{extension}"));
+
+    Some(String::from(output.to_string_lossy()))
+}
+
+/// Uses the output path provided by the user, or creates one from the input path when it is empty.
+pub fn output_path_for(input_path: &str, output_path: &str, extension: &str) -> Option<String> {
+    if output_path.trim().is_empty() {
+        return output_from_input_path(input_path, extension);
+    }
+
+    let mut output_buf = PathBuf::from(output_path.trim());
+    if output_buf.extension().is_none() {
+        output_buf.set_extension(extension);
+    }
+    Some(String::from(output_buf.to_string_lossy()))
+}
+
+/// Directory the downloaded media is saved into when no output is selected: the download directory
+/// of the user, or the home directory when the system has none
+pub fn default_download_dir() -> Option<PathBuf> {
+    let dirs = directories_next::UserDirs::new()?;
+    Some(dirs.download_dir().unwrap_or(dirs.home_dir()).to_path_buf())
+}
+
+/// Path in the temporary directory starting with the `prefix`, unique for every process and every call
+pub(crate) fn unique_temp_path(prefix: &str) -> PathBuf {
+    // The clock is too coarse on some systems to tell apart the calls made right after each other
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_nanos())
+        .unwrap_or_default();
+    std::env::temp_dir().join(format!("{prefix}-{}-{nanos}-{count}", std::process::id()))
+}
+
+/// Escapes the value (a path or a text) used as an option of a filter. Special characters are escaped once
+/// for the option value and once more for the whole filter graph.
+fn escape_filter_value(value: &str) -> String {
+    let mut option = String::new();
+    for c in value.chars() {
+        if matches!(c, '\\' | '\'' | ':') {
+            option.push('\\');
+        }
+        option.push(c);
+    }
+
+    let mut escaped = String::new();
+    for c in option.chars() {
+        if matches!(c, '\\' | '\'' | '[' | ']' | ',' | ';') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
@@ -0,0 +1,1005 @@
+use std::{
+    fs, io::{BufRead, BufReader, Read}, path::{Path, PathBuf}, process::{Command, Stdio}, sync::mpsc::Sender,
+};
+
+use crate::MediaInfo;
+use crate::progress::{ProgressReader, DownloadProgress, YTDLP_PROGRESS_TEMPLATE};
+use super::*;
+use super::runner::probe_media;
+use super::animation::{palette_path, AnimationFormat, AnimationOptions};
+use super::crop::{detect_crop, Crop, CropRect};
+use super::thumbnail::{contact_sheet_filter, ThumbnailKind};
+use super::trim::format_seconds;
+use super::bitrate::{MAX_SIZE_ATTEMPTS, SIZE_RETRY_MARGIN};
+use super::loudness::{Loudnorm, LoudnessMeasurement};
+use super::frames::thumbnail_times;
+use super::subtitles::{extraction_format, extraction_path, BurnIn, SubtitleTracks};
+
+/// Output of the first pass of the two-pass encoding, only its log file is needed
+const NULL_OUTPUT: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+/// Keeps ffmpeg off the terminal, it must not read the input of the shell nor print over the progress
+const QUIET_ARGS: [&str; 5] = [ "-nostdin", "-hide_banner", "-nostats", "-loglevel", "error" ];
+
+impl DownloadJob {
+    pub(super) fn download_resource(&self, channels: ThreadChannels) {
+        let Some(ytdlp) = channels.runner.find_program("yt-dlp") else {
+            channels.send_error("The downloader could not be found. Please re-run the app to download required dependencies");
+            return;
+        };
+
+        let mut process = Command::new(ytdlp);
+        process.args([ "--progress", "--quiet", "--newline" ]);
+        process.args([ "--progress-template", YTDLP_PROGRESS_TEMPLATE ]);
+
+        if !self.output_file.is_empty() {
+            process.args([ "-o", &self.output_file ]);
+        }
+        
+        process.arg(&self.input_link);
+
+        std::thread::spawn(move || {
+            match run_download(process, &channels, ProgressRange::FULL) {
+                Ok(()) => channels.send_success("Download successful"),
+                Err(error) => error.report_download(&channels),
+            }
+        });
+    }
+}
+
+/// The ffmpeg processes of a single convertion, run one after another (for example the two passes
+/// of a two-pass encoding).
+#[derive(Default)]
+struct ConvertionPlan {
+    passes: Vec<Command>,
+    /// Duration of the converted media in ms, each of the passes goes through all of it
+    media_duration: u64,
+    /// Run before the passes when the loudness is normalized
+    loudness: Option<LoudnessPass>,
+    /// Files written by the passes for the passes that follow, removed once the convertion is done
+    temp_files: Vec<PathBuf>,
+    /// Options the animation is encoded with, set only in the Animation mode
+    animation: Option<AnimationOptions>,
+    /// Part of the video kept by the crop, the detected crop is reused by the next size attempts
+    crop: Option<CropRect>,
+}
+
+/// First pass of the loudness normalization. It only measures the loudness of the input, the measured
+/// values are applied by the filter added to the last pass of the plan.
+struct LoudnessPass {
+    analysis: Command,
+    loudnorm: Loudnorm,
+    /// Sample rate of the source, the normalized audio is resampled back to it
+    sample_rate: u32,
+    /// Loudness measured by an earlier size attempt, the analysis is not run again then
+    measured: Option<LoudnessMeasurement>,
+}
+
+/// Part of the job progress covered by a single stage of the job. The Dual Mode for example spends
+/// the first half of the progress bar on the download and the second half on the convertion.
+#[derive(Clone, Copy)]
+struct ProgressRange {
+    start: f32,
+    end: f32,
+}
+
+impl ProgressRange {
+    const FULL: Self = Self { start: 0.0, end: 1.0 };
+
+    fn map(&self, fraction: f32) -> f32 {
+        self.start + (self.end - self.start) * fraction
+    }
+}
+
+impl ConvertionJob {
+    pub(super) fn compress(&self, channels: ThreadChannels) {
+        // The plan is made on the job thread as well, the crop detection decodes parts of the video
+        let job = self.clone();
+        std::thread::spawn(move || {
+            let plan = match job.plan(channels.runner.as_ref()) {
+                Ok(plan) => plan,
+                Err(message) => {
+                    channels.send_error(message);
+                    return;
+                }
+            };
+
+            // Jobs of a batch write into subdirectories mirroring the input directory, which may not exist yet
+            if let Some(parent) = Path::new(&job.output_file).parent() {
+                if !parent.as_os_str().is_empty() && fs::create_dir_all(parent).is_err() {
+                    channels.send_error("Failed to create the output directory.");
+                    return;
+                }
+            }
+
+            match run_verified_convertion(&job, plan, &channels, ProgressRange::FULL) {
+                Ok(true)   => channels.send_success("Convertion finished successfully"),
+                Ok(false)  => channels.send_warning(SIZE_EXCEEDED_MESSAGE),
+                Err(error) => error.report(&channels),
+            }
+        });
+    }
+
+    fn plan(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        // The thumbnails are taken from the whole video, the trim is not used there
+        if matches!(self.mode, AppMode::Thumbnail) {
+            return self.plan_thumbnail(runner);
+        }
+
+        // The animations are always encoded, even when the fast cut is selected
+        if matches!(self.mode, AppMode::Animation) {
+            return self.plan_animation(runner);
+        }
+
+        if self.trim.is_stream_copy() && !matches!(self.mode, AppMode::Download) {
+            return self.plan_stream_copy(runner);
+        }
+
+        match self.mode {
+            AppMode::Audio => self.plan_audio(runner),
+            AppMode::Video => self.plan_video(runner),
+            AppMode::Advanced => self.plan_advanced(runner),
+            AppMode::Download | AppMode::Thumbnail | AppMode::Animation => Err("Selected mode can not be used for the convertion."),
+        }
+    }
+
+    /// Finds ffmpeg and probes the input file. The returned ffmpeg process has nothing but the progress set up.
+    fn probe_input(&self, runner: &dyn ProcessRunner) -> Result<(Command, MediaInfo), &'static str> {
+        let Some(ffmpeg) = runner.find_program("ffmpeg") else {
+            return Err("FFMPEG could not be found. Please re-run the app to download required dependencies");
+        };
+
+        let Some(media_info) = probe_media(runner, &self.input_file) else { 
+            return Err("Failed to retreive media info about the provided file.");
+        }; 
+
+        let mut process = Command::new(ffmpeg);
+        process.args(QUIET_ARGS);
+        process.args([ "-progress", "pipe:1" ]);
+        Ok((process, media_info))
+    }
+
+    /// Finds ffmpeg, probes the input file and creates the ffmpeg process with the input set up.
+    fn prepare_ffmpeg(&self, runner: &dyn ProcessRunner) -> Result<(Command, MediaInfo), &'static str> {
+        let (mut process, media_info) = self.probe_input(runner)?;
+        process.args(self.trim.input_args());
+        process.args([ "-i", &self.input_file, "-y" ]);
+        process.args(self.trim.output_args());
+
+        Ok((process, media_info))
+    }
+
+    /// Cuts the media without encoding, the streams are copied into the output
+    fn plan_stream_copy(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        if self.output_file_size.is_some() {
+            return Err("Fast cuts do not encode the media, so the output size can not be selected.");
+        }
+
+        if self.loudnorm.is_some() && matches!(self.mode, AppMode::Audio | AppMode::Video) {
+            return Err("Fast cuts do not encode the media, so the loudness can not be normalized.");
+        }
+
+        if self.subtitles.burn != BurnIn::None && matches!(self.mode, AppMode::Video) {
+            return Err("Fast cuts do not encode the media, so the subtitles can not be burned in.");
+        }
+
+        if self.frame_rate != FrameRate::default() && matches!(self.mode, AppMode::Video) {
+            return Err("Fast cuts do not encode the media, so the frame rate can not be changed.");
+        }
+
+        if self.crop != Crop::None && matches!(self.mode, AppMode::Video) {
+            return Err("Fast cuts do not encode the media, so the black bars can not be cropped.");
+        }
+
+        let (mut process, media_info) = self.prepare_ffmpeg(runner)?;
+        let media_duration = self.trimmed_duration(&media_info)?;
+
+        let options = &self.advanced_options;
+        match self.mode {
+            AppMode::Audio => { process.arg("-vn"); }
+            AppMode::Advanced if !options.video_enabled => { process.arg("-vn"); }
+            AppMode::Advanced if !options.audio_enabled => { process.arg("-an"); }
+            _ => {}
+        }
+
+        // Timestamps of the copied packets do not start at zero after the cut
+        process.args([ "-c", "copy", "-avoid_negative_ts", "make_zero" ]);
+        process.arg(&self.output_file);
+        Ok(ConvertionPlan { passes: vec![process], media_duration, ..Default::default() })
+    }
+
+    /// Sample rate the audio is reduced to at the selected audio quality. None when the codec does not
+    /// support it (Opus picks the sample rate on its own from the bitrate) or when it would not be lower.
+    fn quality_sample_rate(&self, media_info: &MediaInfo, codec: AudioCodec) -> Option<u32> {
+        let sample_rate = self.audio_quality.sample_rate()?;
+        let source_rate = media_info.first_audio().and_then(|audio| audio.sample_rate);
+
+        if !codec.supports_sample_rate(sample_rate) || source_rate.is_some_and(|rate| rate <= sample_rate) {
+            return None;
+        }
+
+        Some(sample_rate)
+    }
+
+    /// Measuring pass of the loudness normalization, None when the loudness is not normalized or there is
+    /// no audio. The `process` is the ffmpeg process with nothing but the input set up.
+    fn loudness_pass(&self, process: &Command, media_info: &MediaInfo) -> Result<Option<LoudnessPass>, &'static str> {
+        let (Some(loudnorm), Some(audio)) = (self.loudnorm, media_info.first_audio()) else {
+            return Ok(None);
+        };
+        loudnorm.validate()?;
+
+        let mut analysis = Command::new(process.get_program());
+        analysis.args(process.get_args());
+        // The measured values are printed to the standard error, at a level above the one of the other passes
+        analysis.args([ "-loglevel", "info" ]);
+        analysis.args([ "-vn", "-af", &loudnorm.analysis_filter(), "-f", "null", NULL_OUTPUT ]);
+        analysis.stderr(Stdio::piped());
+
+        let sample_rate = audio.sample_rate.unwrap_or(48000);
+        Ok(Some(LoudnessPass { analysis, loudnorm, sample_rate, measured: None }))
+    }
+
+    /// Passes writing the extracted subtitle tracks into their own files. The `process` is the ffmpeg process
+    /// with nothing but the input set up.
+    fn subtitle_extraction_passes(&self, process: &Command, media_info: &MediaInfo) -> Result<Vec<Command>, &'static str> {
+        let streams = self.subtitles.extract.streams(media_info)?;
+
+        let passes = streams.into_iter().map(|stream| {
+            let (codec, extension) = extraction_format(stream);
+
+            let mut pass = Command::new(process.get_program());
+            pass.args(process.get_args());
+            pass.args([ "-map", &format!("0:{}", stream.index), "-c:s", codec ]);
+            pass.arg(extraction_path(&self.output_file, stream, extension));
+            pass
+        });
+
+        Ok(passes.collect())
+    }
+
+    /// Part of the video kept by the crop, detected first when needed. None when nothing is cropped.
+    fn crop_rect(&self, runner: &dyn ProcessRunner, media_info: &MediaInfo) -> Result<Option<CropRect>, &'static str> {
+        let Some(video) = media_info.first_video() else {
+            return Ok(None);
+        };
+
+        let rect = match self.crop {
+            Crop::None => return Ok(None),
+            Crop::Rect(rect) => rect,
+            Crop::Detect => {
+                let Some(rect) = detect_crop(runner, &self.input_file, media_info, &self.trim) else {
+                    return Err("Failed to detect the black bars of the video.");
+                };
+                rect
+            }
+        };
+
+        let Some((width, height)) = video.display_size() else {
+            return Ok(Some(rect));
+        };
+
+        if !rect.fits(width, height) {
+            return Err("The cropped part does not fit inside the video.");
+        }
+        Ok(Some(rect).filter(|rect| !rect.is_full(width, height)))
+    }
+
+    fn plan_video(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        let (mut process, media_info) = self.prepare_ffmpeg(runner)?;
+
+        let media_duration = self.trimmed_duration(&media_info)?;
+        let loudness = self.loudness_pass(&process, &media_info)?;
+        let crop = self.crop_rect(runner, &media_info)?;
+
+        // The subtitles are extracted before the encoding, every plan below starts with these passes
+        let subtitle_passes = self.subtitle_extraction_passes(&process, &media_info)?;
+        let into_plan = move |passes: Vec<Command>| ConvertionPlan {
+            passes: subtitle_passes.into_iter().chain(passes).collect(), media_duration, loudness, crop, ..Default::default()
+        };
+
+        self.frame_rate.validate()?;
+        let source_fps = media_info.first_video().and_then(|video| video.frame_rate());
+
+        let mut video_filters = Vec::new();
+
+        // Frames are dropped first, so the filters below process fewer of them
+        if let Some(filter) = self.frame_rate.filter(source_fps) {
+            video_filters.push(filter);
+        }
+
+        // The bars are cropped before the subtitles are drawn, so the subtitles end up on the picture
+        if let Some(crop) = crop {
+            video_filters.push(crop.filter());
+        }
+
+        // Subtitles are drawn before the scaling, so they get scaled together with the picture
+        if let Some(filter) = self.subtitles.burn_filter(&self.input_file, &media_info, self.trim.start)? {
+            video_filters.push(filter);
+        }
+
+        // Setting a custom resolution, the size is calculated from the size of the (cropped) source
+        if let (Some(scaling), Some(video)) = (self.scaling, media_info.first_video()) {
+            scaling.validate()?;
+            let video = crop.map_or_else(|| video.clone(), |crop| crop.apply(video));
+            if let Some(filter) = scaling.filter(&video) {
+                video_filters.push(filter);
+            }
+        }
+
+        if !video_filters.is_empty() {
+            process.args([ "-vf", &video_filters.join(",") ]);
+        }
+
+        // Set before the passes are split, so both passes of the two-pass encoding see the same frames
+        if media_info.first_video().is_some() {
+            process.args(self.frame_rate.output_args(source_fps));
+        }
+
+        process.args(self.subtitles.stream_args(&media_info, self.video_codec.container())?);
+
+        // Setting the codec with a custom preset
+        // ffmpeg -i input.mp4 -c:v libx264 -preset fast output.mp4
+        let codec = self.video_codec;
+        process.args([ "-c:v", codec.encoder() ]);
+        process.args(codec.preset_args(PRESET_FFMPEG_STRINGS[self.preset]));
+        process.args([ "-c:a", codec.audio_codec().encoder() ]);
+
+        let output_path = &self.output_file;
+
+        if let Some(bitrates) = self.target_bitrates(&media_info)? {
+            // Media without a video stream is encoded in a single pass
+            let Some(video_bitrate) = bitrates.video else {
+                if let Some(audio_bitrate) = bitrates.audio {
+                    process.args([ "-b:a", &format!("{audio_bitrate}K") ]);
+                }
+                process.arg(output_path);
+                return Ok(into_plan(vec![process]));
+            };
+
+            //ffmpeg -y -i input -c:v libx264 -b:v 2600k -pass 1 -an -f null /dev/null && \
+            //ffmpeg -i input -c:v libx264 -b:v 2600k -pass 2 -c:a aac -b:a 128k output.mp4
+            //
+            // Warning: When using option -an, you may eventually get a segfault or a broken file.
+            //          If so, remove option -an and replace by -vsync cfr to the first pass.
+
+            process.args([ "-b:v", &format!("{video_bitrate}K") ]);
+
+            // SVT-AV1 is encoded in a single pass, the size verification fixes the bitrate when it is missed
+            if !codec.supports_two_pass() {
+                if let Some(audio_bitrate) = bitrates.audio {
+                    process.args([ "-b:a", &format!("{audio_bitrate}K") ]);
+                }
+                process.arg(output_path);
+                return Ok(into_plan(vec![process]));
+            }
+
+            let mut first_pass = Command::new(process.get_program());
+            let mut second_pass = process;
+
+            first_pass.args(second_pass.get_args());
+
+            // The statistics of the first pass are not written to the working directory, where the other
+            // running compressors would overwrite them
+            let log_file = unique_temp_path("compressor-pass");
+
+            // first_pass.args([ "-pass", "1", "-vsync", "cfr", "-f", "null", "/dev/null" ]);
+            first_pass.args(codec.pass_args(1, &log_file));
+            first_pass.args([ "-an", "-f", "null", NULL_OUTPUT ]);
+
+            if let Some(audio_bitrate) = bitrates.audio {
+                second_pass.args([ "-b:a", &format!("{audio_bitrate}K") ]);
+            }
+            second_pass.args(codec.pass_args(2, &log_file));
+            second_pass.arg(output_path);
+
+            let mut plan = into_plan(vec![first_pass, second_pass]);
+            plan.temp_files = codec.pass_log_files(&log_file);
+            Ok(plan)
+        } else {
+            let bitrates = self.quality_bitrates(&media_info);
+
+            if let Some(audio_bitrate) = bitrates.audio {
+                process.args([ "-b:a", &format!("{audio_bitrate}K") ]);
+            }
+
+            if let Some(sample_rate) = self.quality_sample_rate(&media_info, codec.audio_codec()) {
+                process.args([ "-ar", &sample_rate.to_string() ]);
+            }
+
+            // Without the bitrate the quality level selects the constant quality
+            match bitrates.video {
+                Some(video_bitrate) => process.args([ "-b:v", &format!("{video_bitrate}K") ]),
+                None => process.args(codec.crf_args(codec.quality_crf(self.video_quality))),
+            };
+
+            process.arg(output_path);
+            Ok(into_plan(vec![process]))
+        }
+    }
+
+    fn plan_advanced(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        let options = &self.advanced_options;
+        let codec = self.video_codec;
+        options.validate(codec, self.audio_codec)?;
+
+        let (mut process, media_info) = self.prepare_ffmpeg(runner)?;
+
+        let media_duration = self.trimmed_duration(&media_info)?;
+
+        if options.video_enabled {
+            process.args([ "-c:v", codec.encoder() ]);
+            process.args(codec.preset_args(options.preset));
+
+            if !options.tune.is_empty() {
+                process.args([ "-tune", options.tune ]);
+            }
+
+            if options.video_bitrate == 0 {
+                process.args(codec.crf_args(options.crf));
+            } else {
+                process.args([ "-b:v", &format!("{}K", options.video_bitrate) ]);
+            }
+
+            // -2 keeps the aspect ratio while making sure that the dimension stays even
+            if options.video_width != 0 || options.video_height != 0 {
+                let width  = if options.video_width  == 0 { String::from("-2") } else { options.video_width.to_string() };
+                let height = if options.video_height == 0 { String::from("-2") } else { options.video_height.to_string() };
+                process.args([ "-vf", &format!("scale={width}:{height}") ]);
+            }
+        } else {
+            process.arg("-vn");
+        }
+
+        if options.audio_enabled {
+            let audio_codec = if options.video_enabled { codec.audio_codec() } else { self.audio_codec };
+            process.args([ "-c:a", audio_codec.encoder() ]);
+
+            if matches!(audio_codec, AudioCodec::Flac) {
+                process.args([ "-compression_level", &self.flac_compression.to_string() ]);
+            }
+
+            if options.audio_bitrate != 0 {
+                process.args([ "-b:a", &format!("{}K", options.audio_bitrate) ]);
+            }
+
+            if options.audio_sample_rate != 0 {
+                process.args([ "-ar", &options.audio_sample_rate.to_string() ]);
+            }
+        } else {
+            process.arg("-an");
+        }
+
+        process.arg(&self.output_file);
+        Ok(ConvertionPlan { passes: vec![process], media_duration, ..Default::default() })
+    }
+
+    fn plan_audio(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        let (mut process, media_info) = self.prepare_ffmpeg(runner)?;
+
+        let media_duration = self.trimmed_duration(&media_info)?;
+        let loudness = self.loudness_pass(&process, &media_info)?;
+
+        // Containers of the audio codecs can not hold the video (cover images are dropped as well)
+        let codec = self.audio_codec;
+        process.args([ "-vn", "-c:a", codec.encoder() ]);
+
+        if let Some(bitrates) = self.target_bitrates(&media_info)? {
+            if let Some(audio_bitrate) = bitrates.audio {
+                process.args([ "-b:a", &format!("{audio_bitrate}K") ]);
+            }
+        } else if matches!(codec, AudioCodec::Flac) {
+            process.args([ "-compression_level", &self.flac_compression.to_string() ]);
+        } else if !codec.is_lossless() {
+            if let Some(level) = codec.vbr_level(self.audio_quality) {
+                process.args([ "-q:a", &level.to_string() ]);
+            } else if let Some(audio_bitrate) = self.quality_bitrates(&media_info).audio {
+                process.args([ "-b:a", &format!("{audio_bitrate}K") ]);
+            }
+
+            if let Some(sample_rate) = self.quality_sample_rate(&media_info, codec) {
+                process.args([ "-ar", &sample_rate.to_string() ]);
+            }
+        }
+
+        process.arg(&self.output_file);
+        Ok(ConvertionPlan { passes: vec![process], media_duration, loudness, ..Default::default() })
+    }
+
+    fn plan_thumbnail(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        let options = &self.thumbnail_options;
+        options.validate()?;
+
+        let (mut process, media_info) = self.probe_input(runner)?;
+
+        if media_info.first_video().is_none() {
+            return Err("The provided file does not contain any video.");
+        }
+
+        let Some(duration) = media_info.duration() else {
+            return Err("Failed to retreive media length from the provided file.");
+        };
+
+        match options.kind {
+            ThumbnailKind::Frame(time) => {
+                let time = time.unwrap_or(duration / 2);
+                if time >= duration {
+                    return Err("The selected time is past the end of the media.");
+                }
+
+                process.args([ "-ss", &format_seconds(time), "-i", &self.input_file, "-y", "-frames:v", "1" ]);
+                if options.width != 0 {
+                    process.args([ "-vf", &format!("scale={}:-2", options.width) ]);
+                }
+            }
+            ThumbnailKind::ContactSheet { columns, rows, timestamps } => {
+                // Seeking every input to its frame is much faster than decoding the whole video
+                let times = thumbnail_times(duration, (columns * rows) as usize);
+                for time in &times {
+                    process.args([ "-ss", &format_seconds(*time), "-i", &self.input_file ]);
+                }
+
+                let filter = contact_sheet_filter(&times, columns, rows, options.width, timestamps);
+                process.args([ "-y", "-filter_complex", &filter, "-frames:v", "1" ]);
+            }
+        }
+
+        process.args(options.format.encoder_args());
+        process.arg(&self.output_file);
+
+        // A single image is written once everything is decoded, so any progress means that the job is done
+        Ok(ConvertionPlan { passes: vec![process], media_duration: 1, ..Default::default() })
+    }
+
+    fn plan_animation(&self, runner: &dyn ProcessRunner) -> Result<ConvertionPlan, &'static str> {
+        self.animation_options.validate()?;
+
+        let (process, media_info) = self.prepare_ffmpeg(runner)?;
+
+        let Some(video) = media_info.first_video() else {
+            return Err("The provided file does not contain any video.");
+        };
+        let media_duration = self.trimmed_duration(&media_info)?;
+        let options = self.animation_options.capped(video.frame_rate());
+
+        let (mut passes, mut animation, temp_files) = match options.format {
+            AnimationFormat::Gif => {
+                // The palette is the second input, so the output options of the trim go after it
+                let palette = palette_path();
+                let mut animation = Command::new(process.get_program());
+                animation.args(QUIET_ARGS);
+                animation.args([ "-progress", "pipe:1" ]);
+                animation.args(self.trim.input_args());
+                animation.args([ "-i", &self.input_file ]);
+                animation.arg("-i").arg(&palette);
+                animation.arg("-y");
+                animation.args(self.trim.output_args());
+                animation.args([ "-an", "-sn", "-lavfi", &options.gif_filter() ]);
+
+                // The first pass only writes the palette of the whole clip
+                let mut palette_pass = process;
+                palette_pass.args([ "-an", "-sn", "-vf", &options.palette_filter(), "-update", "1" ]);
+                palette_pass.arg(&palette);
+
+                (vec![palette_pass], animation, vec![palette])
+            }
+            AnimationFormat::Webp => {
+                let mut animation = process;
+                animation.args([ "-an", "-sn", "-vf", &options.webp_filter() ]);
+                animation.args([ "-c:v", "libwebp_anim", "-lossless", "0", "-quality", "75" ]);
+
+                (Vec::new(), animation, Vec::new())
+            }
+        };
+
+        animation.args([ "-loop", &options.loop_arg() ]);
+        animation.arg(&self.output_file);
+        passes.push(animation);
+
+        Ok(ConvertionPlan { passes, media_duration, temp_files, animation: Some(options), ..Default::default() })
+    }
+}
+
+impl DualJob {
+    pub(super) fn download_and_convert(&self, channels: ThreadChannels) {
+        let Some(ytdlp) = channels.runner.find_program("yt-dlp") else {
+            channels.send_error("The downloader could not be found. Please re-run the app to download required dependencies");
+            return;
+        };
+
+        // Every job gets its own directory, so the downloaded file is the only file in there
+        let temp_dir = unique_temp_path("compressor");
+        if fs::create_dir_all(&temp_dir).is_err() {
+            channels.send_error("Failed to create a temporary directory for the download.");
+            return;
+        }
+
+        let mut process = Command::new(ytdlp);
+        process.args([ "--progress", "--quiet", "--newline", "--no-playlist" ]);
+        process.args([ "--progress-template", YTDLP_PROGRESS_TEMPLATE ]);
+
+        // No need to download the video when it is going to be dropped anyway
+        if matches!(self.convertion.mode, AppMode::Audio) {
+            process.args([ "-f", "bestaudio/best" ]);
+        }
+
+        process.arg("-o").arg(temp_dir.join("%(title)s.%(ext)s"));
+        process.arg(&self.input_link);
+
+        let job = self.convertion.clone();
+        let output_dir = self.output_dir.clone();
+        std::thread::spawn(move || {
+            let result = run_dual_job(process, job, &temp_dir, &output_dir, &channels);
+
+            // The downloaded file (or the partial one on cancel) is never needed after the convertion
+            let _ = fs::remove_dir_all(&temp_dir);
+
+            match result {
+                Ok(true)  => channels.send_success("Download and convertion finished successfully"),
+                Ok(false) => channels.send_warning(SIZE_EXCEEDED_MESSAGE),
+                Err(DualError::Download(error)) => error.report_download(&channels),
+                Err(DualError::Convert(error))  => error.report(&channels),
+                Err(DualError::Message(message)) => channels.send_error(message),
+            }
+        });
+    }
+}
+
+enum DualError {
+    Download(ProcessError),
+    Convert(ConvertionError),
+    Message(&'static str),
+}
+
+/// Returns false when the size of the converted file could not be brought under the selected size
+fn run_dual_job(
+    download: Command, mut job: ConvertionJob, temp_dir: &Path, output_dir: &Path, channels: &ThreadChannels,
+) -> Result<bool, DualError> {
+    const DOWNLOAD_RANGE: ProgressRange = ProgressRange { start: 0.0, end: 0.5 };
+    const CONVERT_RANGE: ProgressRange  = ProgressRange { start: 0.5, end: 1.0 };
+
+    run_download(download, channels, DOWNLOAD_RANGE).map_err(DualError::Download)?;
+
+    let Some(downloaded_file) = find_downloaded_file(temp_dir) else {
+        return Err(DualError::Message("Failed to find the downloaded file."));
+    };
+    job.input_file = downloaded_file.to_string_lossy().to_string();
+
+    // The downloaded file lives in the temporary directory, so the output goes to the output directory
+    if job.output_file.is_empty() {
+        let output = output_from_input_path(&job.input_file, &job.output_extension())
+            .and_then(|output| Some(output_dir.join(Path::new(&output).file_name()?)));
+
+        let Some(output) = output else {
+            return Err(DualError::Message("Failed to construct output path for the downloaded file."));
+        };
+        job.output_file = output.to_string_lossy().to_string();
+    }
+
+    let plan = job.plan(channels.runner.as_ref()).map_err(DualError::Message)?;
+    run_verified_convertion(&job, plan, channels, CONVERT_RANGE).map_err(DualError::Convert)
+}
+
+/// The largest file in the directory, skipping the partial downloads of yt-dlp
+fn find_downloaded_file(directory: &Path) -> Option<PathBuf> {
+    fs::read_dir(directory).ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .filter(|entry| {
+            let path = entry.path();
+            let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+            !matches!(extension, "part" | "ytdl")
+        })
+        .max_by_key(|entry| entry.metadata().map(|meta| meta.len()).unwrap_or(0))
+        .map(|entry| entry.path())
+}
+
+const SIZE_EXCEEDED_MESSAGE: &str = "Convertion finished, but the output is still larger than the selected size";
+
+enum ConvertionError {
+    Process(ProcessError),
+    Message(&'static str),
+}
+
+impl ConvertionError {
+    fn report(&self, channels: &ThreadChannels) {
+        match self {
+            ConvertionError::Process(error)   => error.report(channels),
+            ConvertionError::Message(message) => channels.send_error(message),
+        }
+    }
+}
+
+/// Runs the convertion. With the size verification enabled, the output file is measured after every
+/// attempt and encoded again with a proportionally smaller target size, until it fits or the attempts
+/// run out. Returns false when the output is still larger than the selected size after the last attempt.
+fn run_verified_convertion(
+    job: &ConvertionJob, plan: ConvertionPlan, channels: &ThreadChannels, range: ProgressRange,
+) -> Result<bool, ConvertionError> {
+    // Nothing but the verification can bring the animations to the selected size
+    let verify = job.mode.uses_target_size() && (job.verify_output_size || matches!(job.mode, AppMode::Animation));
+    let size_limit = match job.output_file_size {
+        Some(size) if verify => (size * 1_000_000.0) as u64,
+        _ => {
+            run_convertion(plan, &job.output_file, channels, range, None)?;
+            return Ok(true);
+        }
+    };
+
+    let mut job = job.clone();
+    let mut plan = plan;
+    for attempt in 1..=MAX_SIZE_ATTEMPTS {
+        let animation = plan.animation.clone();
+
+        // The black bars are detected and the subtitles are extracted only once
+        job.crop = plan.crop.map_or(Crop::None, Crop::Rect);
+        job.subtitles.extract = SubtitleTracks::None;
+        let loudness = run_convertion(plan, &job.output_file, channels, range, Some(attempt))?;
+
+        let Ok(metadata) = fs::metadata(&job.output_file) else {
+            return Err(ConvertionError::Message("Failed to measure the size of the output file."));
+        };
+
+        let output_size = metadata.len();
+        if output_size <= size_limit {
+            return Ok(true);
+        }
+
+        if attempt == MAX_SIZE_ATTEMPTS {
+            break;
+        }
+
+        let target_size = job.output_file_size.unwrap_or_default();
+        let correction = size_limit as f32 / output_size as f32;
+        if let Some(mut animation) = animation {
+            animation.shrink(correction * SIZE_RETRY_MARGIN);
+            job.animation_options = animation;
+        } else {
+            job.output_file_size = Some(target_size * correction * SIZE_RETRY_MARGIN);
+        }
+        plan = job.plan(channels.runner.as_ref()).map_err(ConvertionError::Message)?;
+
+        // Only the size changes, so the loudness measured by the first attempt is still right
+        if let Some(pass) = &mut plan.loudness {
+            pass.measured = loudness;
+        }
+    }
+
+    Ok(false)
+}
+
+/// Runs all of the passes of the convertion. A canceled convertion removes the partial output file.
+/// Returns the measured loudness when the loudness was normalized.
+fn run_convertion(
+    mut plan: ConvertionPlan, output_file: &str, channels: &ThreadChannels, range: ProgressRange, size_attempt: Option<u32>,
+) -> Result<Option<LoudnessMeasurement>, ConvertionError> {
+    let temp_files = std::mem::take(&mut plan.temp_files);
+    let result = run_passes(plan, output_file, channels, range, size_attempt);
+
+    // Removed whether the convertion succeeded or not
+    for file in temp_files {
+        let _ = fs::remove_file(file);
+    }
+    result
+}
+
+/// Runs the passes of the plan one after another
+fn run_passes(
+    plan: ConvertionPlan, output_file: &str, channels: &ThreadChannels, range: ProgressRange, size_attempt: Option<u32>,
+) -> Result<Option<LoudnessMeasurement>, ConvertionError> {
+    let analysis_count = plan.loudness.as_ref().is_some_and(|loudness| loudness.measured.is_none()) as u64;
+    let total_duration = plan.media_duration * (plan.passes.len() as u64 + analysis_count);
+    let mut passes = plan.passes;
+
+    // The loudness is measured before everything else, the last pass applies the measured values
+    let mut measured_loudness = None;
+    if let Some(loudness) = plan.loudness {
+        let measured = match loudness.measured {
+            Some(measured) => measured,
+            None => {
+                let log = run_ffmpeg_pass(loudness.analysis, channels, 0, total_duration, range, size_attempt)
+                    .map_err(ConvertionError::Process)?;
+
+                let Some(measured) = LoudnessMeasurement::from_ffmpeg_output(&log) else {
+                    return Err(ConvertionError::Message("Failed to measure the loudness of the audio."));
+                };
+                measured
+            }
+        };
+
+        if let Some(last_pass) = passes.pop() {
+            let filter = loudness.loudnorm.filter(&measured, loudness.sample_rate);
+            passes.push(with_args_before_output(last_pass, &[ "-af", &filter ]));
+        }
+        measured_loudness = Some(measured);
+    }
+
+    for (i, pass) in passes.into_iter().enumerate() {
+        // Adding media duration of the passes that are already done
+        let pass_offset = plan.media_duration * (i as u64 + analysis_count);
+
+        if let Err(error) = run_ffmpeg_pass(pass, channels, pass_offset, total_duration, range, size_attempt) {
+            if matches!(error, ProcessError::Canceled) {
+                let _ = fs::remove_file(output_file);
+            }
+            return Err(ConvertionError::Process(error));
+        }
+    }
+
+    Ok(measured_loudness)
+}
+
+/// Copy of the command with the `args` placed right before the output file, which is the last argument
+fn with_args_before_output(command: Command, args: &[&str]) -> Command {
+    let mut command_args: Vec<_> = command.get_args().collect();
+    let output = command_args.pop();
+
+    let mut new_command = Command::new(command.get_program());
+    new_command.args(command_args).args(args).args(output);
+    new_command
+}
+
+enum ProcessError {
+    Spawn,
+    Wait,
+    Failed,
+    Canceled,
+}
+
+impl ProcessError {
+    fn report(&self, channels: &ThreadChannels) {
+        match self {
+            ProcessError::Spawn    => channels.send_error("Failed to start the the convertion process."),
+            ProcessError::Wait     => channels.send_error("Convertion unexpectedly stopped"),
+            ProcessError::Failed   => channels.send_error("Convertion process failed"),
+            ProcessError::Canceled => channels.send_canceled("Convertion process was canceled"),
+        }
+    }
+
+    fn report_download(&self, channels: &ThreadChannels) {
+        match self {
+            ProcessError::Spawn    => channels.send_error("Failed to start the downloading"),
+            ProcessError::Wait     => channels.send_error("Downloading unexpectedly stopped"),
+            ProcessError::Failed   => channels.send_error("Failed to download the file"),
+            ProcessError::Canceled => channels.send_canceled("Downloading process was canceled"),
+        }
+    }
+}
+
+/// Runs the process until it exits or the job gets canceled. Reading the output blocks until the
+/// process prints something, so `read_output` is run on a separate thread and sends everything it
+/// parsed to `on_output`, which is polled here together with the abort signal.
+/// Returns the standard error of the process when the command piped it (empty otherwise).
+fn run_process<T: Send + 'static>(
+    command: Command,
+    channels: &ThreadChannels,
+    read_output: impl FnOnce(Box<dyn Read + Send>, Sender<T>) + Send + 'static,
+    mut on_output: impl FnMut(T),
+) -> Result<String, ProcessError> {
+    let Ok(mut process) = channels.runner.spawn(command) else {
+        return Err(ProcessError::Spawn);
+    };
+
+    let (output_tx, output_rx) = std::sync::mpsc::channel();
+    if let Some(stdout) = process.take_stdout() {
+        std::thread::spawn(move || read_output(stdout, output_tx));
+    } else {
+        // Nothing is going to be sent, the channel has to be closed for the drain below to end
+        drop(output_tx);
+    }
+
+    // The standard error has to be read while the process runs, otherwise the process blocks once the pipe is full
+    let log_reader = process.take_stderr().map(|mut stderr| std::thread::spawn(move || {
+        let mut log = Vec::new();
+        let _ = stderr.read_to_end(&mut log);
+        String::from_utf8_lossy(&log).to_string()
+    }));
+
+    let success = loop {
+        if channels.is_canceled() {
+            let _ = process.kill();
+            return Err(ProcessError::Canceled);
+        }
+
+        let Ok(wait_result) = process.try_wait() else {
+            return Err(ProcessError::Wait);
+        };
+
+        if let Some(success) = wait_result {
+            break success;
+        }
+
+        if let Ok(output) = output_rx.recv_timeout(std::time::Duration::from_millis(100)) {
+            on_output(output);
+        }
+    };
+
+    // Whatever was printed right before the exit (the final progress block) is still waiting in the channel.
+    // The channel is closed once the reading thread reaches the end of the output.
+    for output in output_rx.iter() {
+        on_output(output);
+    }
+
+    if !success {
+        return Err(ProcessError::Failed);
+    }
+
+    let log = log_reader.and_then(|reader| reader.join().ok()).unwrap_or_default();
+    Ok(log)
+}
+
+/// Runs a single ffmpeg process (with "-progress pipe:1" set) until it exits, sending its progress
+/// to the GUI thread. Passes that were already done are covered by the `pass_offset` (in ms) and the
+/// `total_duration` (in ms) is the media duration of all of the passes combined. Returns the log of ffmpeg
+/// when its standard error was piped.
+fn run_ffmpeg_pass(
+    command: Command, channels: &ThreadChannels, pass_offset: u64, total_duration: u64, range: ProgressRange, size_attempt: Option<u32>,
+) -> Result<String, ProcessError> {
+    let total_duration = total_duration.max(1) as f32;
+
+    let read_output = |stdout: Box<dyn Read + Send>, output_tx: Sender<_>| {
+        for block in ProgressReader::new(BufReader::new(stdout)) {
+            if output_tx.send(block).is_err() {
+                break;
+            }
+        }
+    };
+
+    run_process(command, channels, read_output, |block| {
+        let current_duration = pass_offset as f32 + block.out_time.as_millis() as f32;
+        let fraction = range.map((current_duration / total_duration).clamp(0.0, 1.0));
+
+        // The speed is relative to the media time, so the remaining media time is divided by it.
+        let eta = block.speed.filter(|speed| *speed > 0.0).map(|speed| {
+            let remaining_ms = (total_duration - current_duration).max(0.0) / speed;
+            std::time::Duration::from_millis(remaining_ms as u64)
+        });
+
+        let progress = WorkProgress {
+            fraction,
+            eta,
+            speed: block.speed,
+            fps: block.fps,
+            size_attempt,
+            ..Default::default()
+        };
+        channels.send_progress(progress);
+    })
+}
+
+/// Runs yt-dlp (with YTDLP_PROGRESS_TEMPLATE set) until it exits, sending its progress to the GUI thread.
+fn run_download(command: Command, channels: &ThreadChannels, range: ProgressRange) -> Result<(), ProcessError> {
+    let read_output = |stdout: Box<dyn Read + Send>, output_tx: Sender<_>| {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+
+            let Some(progress) = DownloadProgress::parse_line(&line) else {
+                continue;
+            };
+
+            if output_tx.send(progress).is_err() {
+                break;
+            }
+        }
+    };
+
+    let mut last_fraction = 0.0;
+    run_process(command, channels, read_output, |download: DownloadProgress| {
+        // Progress is unknown for some of the live streams, the previous value is kept then.
+        if let Some(fraction) = download.fraction() {
+            last_fraction = fraction;
+        }
+
+        let progress = WorkProgress {
+            fraction: range.map(last_fraction),
+            eta: download.eta,
+            download_speed: download.speed,
+            current_file: Some(download.filename).filter(|name| !name.is_empty()),
+            ..Default::default()
+        };
+        channels.send_progress(progress);
+    })?;
+
+    Ok(())
+}
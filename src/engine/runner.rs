@@ -0,0 +1,137 @@
+//! Starting of the external programs (ffmpeg, ffprobe and yt-dlp). All of the processes of the engine
+//! are started through the `ProcessRunner`, so the real programs can be replaced, for example in tests.
+
+use std::{
+    io::Read, process::{Child, Command, Stdio},
+};
+
+use crate::{get_command_string, MediaInfo};
+
+pub trait ProcessRunner: Send + Sync {
+    /// Resolves the name of the program ("ffmpeg", "yt-dlp") into the command starting it.
+    /// Returns None when the program could not be found.
+    fn find_program(&self, name: &str) -> Option<String>;
+
+    /// Starts the command with its standard output piped. The standard error is piped only when the
+    /// command asks for it.
+    fn spawn(&self, command: Command) -> std::io::Result<Box<dyn RunningProcess>>;
+}
+
+pub trait RunningProcess: Send {
+    /// The standard output of the process, it can be taken only once.
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+
+    /// The standard error of the process, when it was piped. It can be taken only once.
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        None
+    }
+
+    /// Returns None while the process is still running, otherwise whether it exited successfully.
+    fn try_wait(&mut self) -> std::io::Result<Option<bool>>;
+
+    /// Kills the process and waits until it exits.
+    fn kill(&mut self) -> std::io::Result<()>;
+}
+
+/// Runs the real programs with `std::process::Command`
+pub struct SystemRunner;
+
+impl ProcessRunner for SystemRunner {
+    fn find_program(&self, name: &str) -> Option<String> {
+        get_command_string(name)
+    }
+
+    fn spawn(&self, mut command: Command) -> std::io::Result<Box<dyn RunningProcess>> {
+        // None of the programs reads the standard input, it is left to the terminal
+        command.stdin(Stdio::null());
+        command.stdout(Stdio::piped());
+        let child = command.spawn()?;
+        Ok(Box::new(SystemProcess(child)))
+    }
+}
+
+struct SystemProcess(Child);
+
+impl RunningProcess for SystemProcess {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        let stdout = self.0.stdout.take()?;
+        Some(Box::new(stdout))
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        let stderr = self.0.stderr.take()?;
+        Some(Box::new(stderr))
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<bool>> {
+        Ok(self.0.try_wait()?.map(|status| status.success()))
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        self.0.kill()?;
+        self.0.wait()?;
+        Ok(())
+    }
+}
+
+/// Runs the command until it exits and returns everything it printed. Returns None when the process
+/// could not be started or when it failed.
+pub fn read_output(runner: &dyn ProcessRunner, command: Command) -> Option<String> {
+    String::from_utf8(read_output_bytes(runner, command)?).ok()
+}
+
+/// Same as read_output, for the programs printing binary data (for example the raw video frames).
+pub fn read_output_bytes(runner: &dyn ProcessRunner, command: Command) -> Option<Vec<u8>> {
+    let mut process = runner.spawn(command).ok()?;
+
+    let mut output = Vec::new();
+    if let Some(mut stdout) = process.take_stdout() {
+        stdout.read_to_end(&mut output).ok()?;
+    }
+
+    // The output ends once the process closes it, which normally happens when the process exits
+    let success = loop {
+        if let Some(success) = process.try_wait().ok()? {
+            break success;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    success.then_some(output)
+}
+
+/// Same as read_output, for the commands printing what they found to the standard error (the command
+/// has to pipe it). Returns the standard error of the process.
+pub fn read_log(runner: &dyn ProcessRunner, command: Command) -> Option<String> {
+    let mut process = runner.spawn(command).ok()?;
+
+    let mut log = Vec::new();
+    if let Some(mut stderr) = process.take_stderr() {
+        stderr.read_to_end(&mut log).ok()?;
+    }
+
+    // Nothing else is printed to the standard output, it only has to be closed
+    if let Some(mut stdout) = process.take_stdout() {
+        stdout.read_to_end(&mut Vec::new()).ok()?;
+    }
+
+    let success = loop {
+        if let Some(success) = process.try_wait().ok()? {
+            break success;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    success.then(|| String::from_utf8_lossy(&log).to_string())
+}
+
+/// Probes the media file with ffprobe. Returns None when ffprobe is missing or fails to read the file.
+pub fn probe_media(runner: &dyn ProcessRunner, media_path: &str) -> Option<MediaInfo> {
+    let ffprobe = runner.find_program("ffprobe")?;
+
+    let mut command = Command::new(ffprobe);
+    command.args([ "-v", "quiet", "-print_format", "json", "-show_format", "-show_streams", media_path ]);
+
+    let output = read_output(runner, command)?;
+    MediaInfo::from_ffprobe_json(&output)
+}
@@ -0,0 +1,139 @@
+//! Scaling of the video. The output size is calculated from the size of the source, so both dimensions
+//! always stay even (required by libx264 and most of the other encoders) and the video is never scaled up
+//! unless it is asked for.
+
+use crate::media_info::StreamInfo;
+
+/// Size the video is scaled to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScaleSize {
+    /// Length of the short side in px: the height of a landscape video or the width of a portrait one
+    ShortSide(u32),
+    /// Exact width and height, the video is placed into it by the ScaleFit
+    Box { width: u32, height: u32 },
+}
+
+/// How the video is placed into the box of the selected size
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum ScaleFit {
+    /// Fits inside the box, keeping the aspect ratio. The output is smaller than the box in one dimension.
+    #[default]
+    Fit,
+    /// Covers the whole box, keeping the aspect ratio. The parts sticking out of the box are cropped.
+    Fill,
+    /// Fits inside the box and the rest of the box is filled with black bars
+    Pad,
+}
+
+pub const SCALE_FITS: &[ScaleFit] = &[ ScaleFit::Fit, ScaleFit::Fill, ScaleFit::Pad ];
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scaling {
+    pub size: ScaleSize,
+    /// Used only with the ScaleSize::Box
+    pub fit: ScaleFit,
+    /// Allows the video to get larger than the source
+    pub upscale: bool,
+}
+
+impl ScaleFit {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            ScaleFit::Fit  => "Fit",
+            ScaleFit::Fill => "Fill (crop)",
+            ScaleFit::Pad  => "Pad (black bars)",
+        }
+    }
+
+    /// Name used in the command line arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScaleFit::Fit  => "fit",
+            ScaleFit::Fill => "fill",
+            ScaleFit::Pad  => "pad",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        SCALE_FITS.iter().copied().find(|fit| fit.name() == name.to_lowercase())
+    }
+}
+
+impl Scaling {
+    /// Scales the short side to the `size`, which is how the resolutions such as 720p are meant
+    pub fn short_side(size: u32) -> Self {
+        Self { size: ScaleSize::ShortSide(size), fit: ScaleFit::default(), upscale: false }
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        match self.size {
+            ScaleSize::ShortSide(size) if !(16..=8192).contains(&size) => {
+                Err("Resolution must be between 16 and 8192.")
+            }
+            ScaleSize::Box { width, height } => {
+                for size in [width, height] {
+                    if !(16..=8192).contains(&size) || size % 2 != 0 {
+                        return Err("Video width and height must be even numbers between 16 and 8192.");
+                    }
+                }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Video filter scaling the `video`, None when the size stays the same or the size of the source is unknown
+    pub fn filter(&self, video: &StreamInfo) -> Option<String> {
+        let (source_width, source_height) = video.display_size()?;
+        if source_width == 0 || source_height == 0 {
+            return None;
+        }
+        let (width, height) = (source_width as f64, source_height as f64);
+
+        let limit = |factor: f64| if self.upscale { factor } else { factor.min(1.0) };
+        let scaled = |factor: f64| (even(width * factor), even(height * factor));
+
+        let mut filters = Vec::new();
+        let mut push_scale = |(scaled_width, scaled_height): (u32, u32)| {
+            if (scaled_width, scaled_height) != (source_width, source_height) {
+                filters.push(format!("scale={scaled_width}:{scaled_height}"));
+            }
+        };
+
+        match self.size {
+            ScaleSize::ShortSide(size) => {
+                push_scale(scaled(limit(size as f64 / width.min(height))));
+            }
+            ScaleSize::Box { width: box_width, height: box_height } => {
+                let (box_width_f, box_height_f) = (box_width as f64, box_height as f64);
+                match self.fit {
+                    ScaleFit::Fit => {
+                        push_scale(scaled(limit((box_width_f / width).min(box_height_f / height))));
+                    }
+                    ScaleFit::Fill => {
+                        let size = scaled(limit((box_width_f / width).max(box_height_f / height)));
+                        push_scale(size);
+
+                        // Without the upscaling the video may be smaller than the box, nothing is cropped then
+                        let crop = (size.0.min(box_width), size.1.min(box_height));
+                        if crop != size {
+                            filters.push(format!("crop={}:{}", crop.0, crop.1));
+                        }
+                    }
+                    ScaleFit::Pad => {
+                        push_scale(scaled(limit((box_width_f / width).min(box_height_f / height))));
+                        filters.push(format!("pad={box_width}:{box_height}:(ow-iw)/2:(oh-ih)/2"));
+                    }
+                }
+            }
+        }
+
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+}
+
+/// Rounds the dimension to the closest even number, never below 2
+fn even(size: f64) -> u32 {
+    ((size / 2.0).round() as u32 * 2).max(2)
+}
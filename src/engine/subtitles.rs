@@ -0,0 +1,162 @@
+//! Subtitles of the Video mode. Tracks of the input can be kept as soft subtitles, burned into the picture
+//! (as well as an external .srt or .ass file) or extracted into separate files. Tracks are always selected
+//! by their stream index, as listed by ffprobe.
+
+use std::path::Path;
+
+use crate::media_info::{MediaInfo, StreamInfo};
+use super::escape_filter_value;
+use super::trim::format_seconds;
+
+/// Codecs of the image-based subtitles, all of the other subtitle codecs are text
+const BITMAP_SUBTITLE_CODECS: &[&str] = &[ "hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub" ];
+
+/// Subtitle tracks of the input, by the stream index
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum SubtitleTracks {
+    #[default]
+    None,
+    All,
+    Selected(Vec<usize>),
+}
+
+/// Subtitles drawn onto the video
+#[derive(Default, Debug, Clone, PartialEq)]
+pub enum BurnIn {
+    #[default]
+    None,
+    /// Subtitle track of the input, by the stream index
+    Track(usize),
+    /// External subtitle file (.srt, .ass and everything else ffmpeg can read)
+    File(String),
+}
+
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Subtitles {
+    /// Tracks copied into the output as soft subtitles, converted to the format supported by the container
+    pub keep: SubtitleTracks,
+    pub burn: BurnIn,
+    /// Tracks written into separate files next to the output
+    pub extract: SubtitleTracks,
+}
+
+pub fn is_text_subtitle(stream: &StreamInfo) -> bool {
+    !BITMAP_SUBTITLE_CODECS.contains(&stream.codec_name.as_str())
+}
+
+/// Name of the track shown in the GUI, for example "#2 pol - Polish (mov_text)"
+pub fn track_label(stream: &StreamInfo) -> String {
+    let mut label = format!("#{}", stream.index);
+    if let Some(language) = stream.language() {
+        label += &format!(" {language}");
+    }
+    if let Some(title) = stream.title() {
+        label += &format!(" - {title}");
+    }
+    label + &format!(" ({})", stream.codec_name)
+}
+
+impl SubtitleTracks {
+    /// Subtitle streams of the selected tracks. Fails when any of the tracks is not a subtitle stream.
+    pub fn streams<'a>(&self, media_info: &'a MediaInfo) -> Result<Vec<&'a StreamInfo>, &'static str> {
+        match self {
+            SubtitleTracks::None => Ok(Vec::new()),
+            SubtitleTracks::All => Ok(media_info.subtitle_streams().collect()),
+            SubtitleTracks::Selected(tracks) => tracks.iter().map(|index| {
+                media_info.subtitle_streams()
+                    .find(|stream| stream.index == *index)
+                    .ok_or("The selected subtitle track does not exist in the provided file.")
+            }).collect(),
+        }
+    }
+}
+
+impl Subtitles {
+    /// Arguments selecting the streams of the output. Once any of the streams is mapped by hand ffmpeg
+    /// stops picking the streams on its own, so the video and the audio are mapped as well.
+    pub fn stream_args(&self, media_info: &MediaInfo, container: &str) -> Result<Vec<String>, &'static str> {
+        let streams = self.keep.streams(media_info)?;
+        if streams.is_empty() {
+            return Ok(vec![ "-sn".into() ]);
+        }
+
+        // Both mp4 and webm only store the text subtitles, each in its own format
+        if streams.iter().any(|stream| !is_text_subtitle(stream)) {
+            return Err("Image-based subtitles can not be kept in the output, burn them in or extract them instead.");
+        }
+        let codec = if container == "webm" { "webvtt" } else { "mov_text" };
+
+        let mut args: Vec<String> = vec![ "-map".into(), "0:v:0?".into(), "-map".into(), "0:a:0?".into() ];
+        for stream in streams {
+            args.extend([ "-map".into(), format!("0:{}", stream.index) ]);
+        }
+        args.extend([ "-c:s".into(), codec.into() ]);
+        Ok(args)
+    }
+
+    /// Video filter drawing the subtitles, None when nothing is burned in. `trim_start` (in ms) is where the
+    /// input is seeked to, the timestamps start from zero after seeking so they are shifted back for the
+    /// filter to pick the right subtitles.
+    pub fn burn_filter(
+        &self, input_file: &str, media_info: &MediaInfo, trim_start: Option<u64>,
+    ) -> Result<Option<String>, &'static str> {
+        let filter = match &self.burn {
+            BurnIn::None => return Ok(None),
+            BurnIn::Track(index) => {
+                // The filter selects the track by its position among the subtitle streams
+                let track = media_info.subtitle_streams().enumerate().find(|(_, stream)| stream.index == *index);
+                let Some((position, stream)) = track else {
+                    return Err("The selected subtitle track does not exist in the provided file.");
+                };
+
+                if !is_text_subtitle(stream) {
+                    return Err("Only text subtitles can be burned into the video.");
+                }
+
+                format!("subtitles={}:si={position}", escape_filter_value(input_file))
+            }
+            BurnIn::File(path) => {
+                if !Path::new(path).is_file() {
+                    return Err("The subtitle file does not exist.");
+                }
+                format!("subtitles={}", escape_filter_value(path))
+            }
+        };
+
+        let filter = match trim_start {
+            Some(start) if start > 0 => {
+                format!("setpts=PTS+{}/TB,{filter},setpts=PTS-STARTPTS", format_seconds(start))
+            }
+            _ => filter,
+        };
+        Ok(Some(filter))
+    }
+}
+
+/// Encoder and the file extension of the extracted track. Text subtitles are converted to SubRip, unless
+/// the format has its own styling (ASS) or is already a plain subtitle file (WebVTT). Image-based subtitles
+/// are copied into a file of their own format.
+pub fn extraction_format(stream: &StreamInfo) -> (&'static str, &'static str) {
+    match stream.codec_name.as_str() {
+        "ass" | "ssa"       => ("copy", "ass"),
+        "webvtt"            => ("copy", "vtt"),
+        "hdmv_pgs_subtitle" => ("copy", "sup"),
+        _ if is_text_subtitle(stream) => ("srt", "srt"),
+        // Matroska is the only common container for the rest of the image-based subtitles
+        _ => ("copy", "mks"),
+    }
+}
+
+/// Path of the extracted track next to the output, for example "video-new.2.pol.srt"
+pub fn extraction_path(output_file: &str, stream: &StreamInfo, extension: &str) -> String {
+    let output = Path::new(output_file);
+    let stem = output.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+
+    let mut file_name = format!("{stem}.{}", stream.index);
+    if let Some(language) = stream.language() {
+        file_name += &format!(".{language}");
+    }
+    file_name += &format!(".{extension}");
+
+    output.with_file_name(file_name).to_string_lossy().to_string()
+}
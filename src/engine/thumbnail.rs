@@ -0,0 +1,131 @@
+//! Still images of the video for the Thumbnail mode: a single frame at the selected time, or a contact sheet
+//! with a grid of evenly spaced frames.
+
+use super::escape_filter_value;
+
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+}
+
+pub const IMAGE_FORMATS: &[ImageFormat] = &[ ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::Webp ];
+
+/// Width (in px) of the frames of the contact sheet when none is selected
+pub const DEFAULT_TILE_WIDTH: u32 = 320;
+/// Highest number of the columns and the rows of the contact sheet
+pub const MAX_SHEET_SIZE: u32 = 10;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ThumbnailKind {
+    /// A single frame at the time in ms, None picks the middle of the video
+    Frame(Option<u64>),
+    /// Grid of the frames spread evenly over the whole video
+    ContactSheet { columns: u32, rows: u32, timestamps: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThumbnailOptions {
+    pub kind: ThumbnailKind,
+    pub format: ImageFormat,
+    /// Width of the frame (every frame of the contact sheet) in px. 0 keeps the width of the video, or uses
+    /// the DEFAULT_TILE_WIDTH in the contact sheet.
+    pub width: u32,
+}
+
+impl Default for ThumbnailOptions {
+    fn default() -> Self {
+        Self {
+            kind: ThumbnailKind::Frame(None),
+            format: ImageFormat::default(),
+            width: 0,
+        }
+    }
+}
+
+impl ImageFormat {
+    /// Name shown in the GUI
+    pub fn label(&self) -> &'static str {
+        match self {
+            ImageFormat::Png  => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Webp => "WebP",
+        }
+    }
+
+    /// Name used in the command line arguments
+    pub fn name(&self) -> &'static str {
+        match self {
+            ImageFormat::Png  => "png",
+            ImageFormat::Jpeg => "jpeg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "jpg" => Some(ImageFormat::Jpeg),
+            name  => IMAGE_FORMATS.iter().copied().find(|format| format.name() == name),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png  => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Webp => "webp",
+        }
+    }
+
+    /// Encoder of the image, the lossy formats are saved at a high quality
+    pub fn encoder_args(&self) -> Vec<String> {
+        let args: &[&str] = match self {
+            ImageFormat::Png  => &[ "-c:v", "png" ],
+            ImageFormat::Jpeg => &[ "-c:v", "mjpeg", "-q:v", "2" ],
+            ImageFormat::Webp => &[ "-c:v", "libwebp", "-quality", "90" ],
+        };
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+}
+
+impl ThumbnailOptions {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let ThumbnailKind::ContactSheet { columns, rows, .. } = self.kind {
+            let sizes = 1..=MAX_SHEET_SIZE;
+            if !sizes.contains(&columns) || !sizes.contains(&rows) {
+                return Err("The contact sheet must have between 1 and 10 columns and rows.");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Filter building the contact sheet out of the inputs, each of them seeked to one of the `times` (in ms).
+/// Every input gives a single frame, the frames are joined one after another and tiled into the grid.
+pub fn contact_sheet_filter(times: &[u64], columns: u32, rows: u32, width: u32, timestamps: bool) -> String {
+    let width = if width == 0 { DEFAULT_TILE_WIDTH } else { width };
+    let font_size = (width / 14).max(12);
+
+    let mut filter = String::new();
+    for (i, time) in times.iter().enumerate() {
+        filter += &format!("[{i}:v]trim=end_frame=1,scale={width}:-2");
+
+        if timestamps {
+            let secs = time / 1000;
+            let text = format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60);
+            filter += &format!(
+                ",drawtext=text={}:x=8:y=h-th-8:fontsize={font_size}:fontcolor=white:box=1:boxcolor=black@0.6:boxborderw=4",
+                escape_filter_value(&text),
+            );
+        }
+        filter += &format!("[f{i}];");
+    }
+
+    for i in 0..times.len() {
+        filter += &format!("[f{i}]");
+    }
+    filter += &format!("concat=n={}:v=1:a=0,tile={columns}x{rows}:padding=4:margin=4", times.len());
+    filter
+}
@@ -0,0 +1,132 @@
+//! Keeping only a part of the media, selected with the start and the end (or the duration) of the part.
+
+use crate::MediaInfo;
+use super::ConvertionJob;
+
+/// How the media is cut
+#[derive(Default, Debug, Copy, Clone, PartialEq)]
+pub enum CutMode {
+    /// The kept part is encoded again, so it starts and ends exactly at the selected times
+    #[default]
+    Accurate,
+    /// The streams are copied without encoding. It is fast, but the start moves to the closest keyframe
+    /// and nothing gets compressed.
+    Fast,
+}
+
+/// Part of the media kept in the output, all times are in ms
+#[derive(Default, Debug, Clone, PartialEq)]
+pub struct Trim {
+    /// None keeps the media from the beginning
+    pub start: Option<u64>,
+    /// None keeps the media until the end
+    pub end: Option<u64>,
+    pub cut_mode: CutMode,
+}
+
+impl CutMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CutMode::Accurate => "Accurate (encoded again)",
+            CutMode::Fast     => "Fast (stream copy)",
+        }
+    }
+}
+
+impl Trim {
+    pub fn is_enabled(&self) -> bool {
+        self.start.is_some() || self.end.is_some()
+    }
+
+    /// The streams are copied instead of being encoded
+    pub fn is_stream_copy(&self) -> bool {
+        self.is_enabled() && self.cut_mode == CutMode::Fast
+    }
+
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if let (Some(start), Some(end)) = (self.start, self.end) {
+            if end <= start {
+                return Err("The end of the trimmed part must be after its start.");
+            }
+        }
+        Ok(())
+    }
+
+    /// Duration of the part kept out of the media lasting `media_duration` ms
+    pub fn trimmed_duration(&self, media_duration: u64) -> Result<u64, &'static str> {
+        self.validate()?;
+
+        let start = self.start.unwrap_or(0);
+        let end = self.end.map_or(media_duration, |end| end.min(media_duration));
+        if start >= end {
+            return Err("The start of the trimmed part is past the end of the media.");
+        }
+
+        Ok(end - start)
+    }
+
+    /// Arguments placed before the input. Seeking in the input skips the decoding of the dropped part.
+    pub fn input_args(&self) -> Vec<String> {
+        match self.start {
+            Some(start) => vec![ "-ss".into(), format_seconds(start) ],
+            None => Vec::new(),
+        }
+    }
+
+    /// Arguments placed after the input. The timestamps start from zero after seeking, so the end is
+    /// passed as the duration.
+    pub fn output_args(&self) -> Vec<String> {
+        match self.end {
+            Some(end) => vec![ "-t".into(), format_seconds(end - self.start.unwrap_or(0).min(end)) ],
+            None => Vec::new(),
+        }
+    }
+}
+
+impl ConvertionJob {
+    /// Duration of the converted part of the media in ms
+    pub fn trimmed_duration(&self, media_info: &MediaInfo) -> Result<u64, &'static str> {
+        let Some(media_duration) = media_info.duration() else {
+            return Err("Failed to retreive media length from the provided file.");
+        };
+        self.trim.trimmed_duration(media_duration)
+    }
+}
+
+/// Formats the time in ms as seconds accepted by ffmpeg, for example "90.500"
+pub(super) fn format_seconds(time: u64) -> String {
+    format!("{}.{:03}", time / 1000, time % 1000)
+}
+
+/// Parses the time typed by the user into ms. Accepts seconds ("90", "90.5"), "MM:SS" and "HH:MM:SS",
+/// the seconds can have a fraction in all of them.
+pub fn parse_time(text: &str) -> Option<u64> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    let mut parts: Vec<&str> = text.split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+
+    let seconds: f64 = parts.pop()?.parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 || (!parts.is_empty() && seconds >= 60.0) {
+        return None;
+    }
+
+    let mut total_minutes = 0;
+    for part in parts {
+        let value: u64 = part.parse().ok()?;
+        total_minutes = total_minutes * 60 + value;
+    }
+
+    Some(total_minutes * 60_000 + (seconds * 1000.0).round() as u64)
+}
+
+/// Formats the time in ms as "HH:MM:SS.mmm", the format accepted by parse_time
+pub fn format_time(time: u64) -> String {
+    let secs = time / 1000;
+    format!("{:02}:{:02}:{:02}.{:03}", secs / 3600, (secs / 60) % 60, secs % 60, time % 1000)
+}
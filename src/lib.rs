@@ -1,12 +1,21 @@
 use std::{
     process::Command,
-    env::current_dir, path::Path, fs::File
+    env::current_dir, path::Path,
 };
 
+#[cfg(feature = "gui")]
 pub mod deps_download;
+#[cfg(feature = "gui")]
 pub mod popup;
 // pub mod compressor;
+#[cfg(feature = "gui")]
 pub mod app;
+pub mod cli;
+pub mod engine;
+pub mod media_info;
+pub mod progress;
+
+pub use media_info::MediaInfo;
 
 pub const QUALIFIER: &str    = "net";
 pub const ORGANIZATION: &str = "Kihau";
@@ -14,46 +23,9 @@ pub const APPLICATION: &str  = "Compressor";
 
 pub const GUI_SCALE: f32 = 1.66;
 
-pub fn get_media_info(media_path: &str) -> Option<String> {
-    let Some(command) = get_command_string("ffmpeg") else {
-        return None;
-    };
-    let media_info = Command::new(command)
-        .args([ "-i", media_path ])
-        .output();
-
-    let Ok(media_info) = media_info else {
-        return None;
-    };
-
-    Some(String::from_utf8_lossy(&media_info.stderr).to_string())
-}
-
-pub fn extract_media_duration(media_string: &str) -> Option<u64> {
-    let Some(start_idx) = media_string.find("Duration:") else {
-        return None;
-    };
-
-    let mut duration = 0u64;
-    let Some(duration_string) = media_string[start_idx..].split(' ').nth(1) else {
-        return None;
-    };
-    let chars: Vec<_> = duration_string.chars().collect();
-
-    // Hours -> ms
-    duration += chars[0].to_digit(10).unwrap() as u64 * 1000 * 3600 * 10;
-    duration += chars[1].to_digit(10).unwrap() as u64 * 1000 * 3600;
-    // Mintues -> ms
-    duration += chars[3].to_digit(10).unwrap() as u64 * 1000 * 60 * 10;
-    duration += chars[4].to_digit(10).unwrap() as u64 * 1000 * 60;
-    // Seconds -> ms
-    duration += chars[6].to_digit(10).unwrap() as u64 * 1000 * 10;
-    duration += chars[7].to_digit(10).unwrap() as u64 * 1000;
-    // Miliseconds
-    duration += chars[9].to_digit(10).unwrap() as u64 * 10;
-    duration += chars[10].to_digit(10).unwrap() as u64;
-
-    Some(duration)
+/// Probes the media file with ffprobe. Returns None when ffprobe is missing or fails to read the file.
+pub fn get_media_info(media_path: &str) -> Option<MediaInfo> {
+    engine::runner::probe_media(&engine::runner::SystemRunner, media_path)
 }
 
 // pub fn get_ffmpeg_string() -> String {
@@ -123,7 +95,6 @@ pub fn get_command_string(cmd_str: &str) -> Option<String> {
         cmd_buf.push(&command);
         // cmd_buf.set_file_name(&command);
 
-        println!("{cmd_buf:?}");
         if cmd_buf.exists() {
             return Some(cmd_buf.to_string_lossy().to_string());
         }
@@ -1,19 +1,52 @@
-// Hide windows console for release builds
+// Hide windows console for release builds, the command line mode attaches to the console of the terminal
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] 
 
+use compressor::cli;
+#[cfg(feature = "gui")]
 use compressor::{get_command_string, deps_download, GUI_SCALE, app::Compressor};
+#[cfg(feature = "gui")]
 use eframe::egui;
 
+fn main() {
+    // Any arguments switch the compressor into the command line mode, no window is ever opened then
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-// TODO: When only directory was provided as an input, convert all files in the directory ???
-// (or maybe just add a radio button "Many")
+    #[cfg(feature = "gui")]
+    if args.is_empty() {
+        if let Err(error) = run_gui() {
+            eprintln!("ERROR: Failed to open the window: {error}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    #[cfg(all(windows, not(debug_assertions)))]
+    attach_console();
+
+    std::process::exit(cli::run(&args));
+}
 
-// TODO: Also allow to run with cli options
+/// Release builds on Windows have no console of their own, so the command line mode prints into the
+/// console of the terminal it was started from. Nothing is printed when it was not started from one.
+#[cfg(all(windows, not(debug_assertions)))]
+fn attach_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
 
-fn main() -> Result<(), eframe::Error> {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
 
+#[cfg(feature = "gui")]
+fn run_gui() -> Result<(), eframe::Error> {
     { // Check whether the required external dependencies are present
-        let ffmpeg_missing = get_command_string("ffmpeg").is_none();
+        // ffprobe comes bundled with ffmpeg
+        let ffmpeg_missing = get_command_string("ffmpeg").is_none() || get_command_string("ffprobe").is_none();
         let ytdlp_missing = get_command_string("yt-dlp").is_none();
 
         if ffmpeg_missing || ytdlp_missing {
@@ -33,9 +66,9 @@ fn main() -> Result<(), eframe::Error> {
 
     let options = eframe::NativeOptions {
         #[cfg(target_os = "windows")]
-        initial_window_size: Some(egui::Vec2::new(500.0 * GUI_SCALE, 400.0 * GUI_SCALE)),
+        initial_window_size: Some(egui::Vec2::new(500.0 * GUI_SCALE, 480.0 * GUI_SCALE)),
         #[cfg(not(target_os = "windows"))]
-        initial_window_size: Some(egui::Vec2::new(300.0 * GUI_SCALE, 240.0 * GUI_SCALE)),
+        initial_window_size: Some(egui::Vec2::new(300.0 * GUI_SCALE, 300.0 * GUI_SCALE)),
 
         resizable: true,
         default_theme: eframe::Theme::Dark, 
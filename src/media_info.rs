@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// Information about a media file, built from the JSON output of:
+///     ffprobe -v quiet -print_format json -show_format -show_streams <file>
+#[derive(Debug, Clone, Default)]
+pub struct MediaInfo {
+    /// Short names of the container format, for example "mov,mp4,m4a,3gp,3g2,mj2"
+    pub format_name: String,
+    pub format_long_name: String,
+    /// Duration of the whole file in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Overall bitrate in bits per second
+    pub bit_rate: Option<u64>,
+    /// Size of the file in bytes
+    pub size: Option<u64>,
+    pub tags: HashMap<String, String>,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+    /// Index of the stream in the file (as used by "-map 0:<index>")
+    pub index: usize,
+    pub kind: StreamKind,
+    pub codec_name: String,
+    pub codec_long_name: String,
+    /// Bitrate of the stream in bits per second. Often missing for streams in mkv and webm files.
+    pub bit_rate: Option<u64>,
+    pub duration_ms: Option<u64>,
+
+    // Video streams
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// Average frame rate of the stream
+    pub avg_frame_rate: Option<f64>,
+    /// The lowest frame rate that can represent all timestamps of the stream
+    pub r_frame_rate: Option<f64>,
+    /// Rotation of the picture in degrees, stored by the phones instead of rotating the frames
+    pub rotation: i32,
+
+    // Audio streams
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+
+    pub tags: HashMap<String, String>,
+}
+
+impl MediaInfo {
+    /// Parses the JSON printed by ffprobe. Returns None when the output is not a valid ffprobe JSON.
+    pub fn from_ffprobe_json(json: &str) -> Option<Self> {
+        let probe: FfprobeOutput = serde_json::from_str(json).ok()?;
+        let format = probe.format.unwrap_or_default();
+
+        let streams = probe.streams.into_iter().map(StreamInfo::from).collect();
+
+        Some(Self {
+            format_name: format.format_name.unwrap_or_default(),
+            format_long_name: format.format_long_name.unwrap_or_default(),
+            duration_ms: format.duration.as_deref().and_then(parse_seconds_to_ms),
+            bit_rate: format.bit_rate.as_deref().and_then(parse_number),
+            size: format.size.as_deref().and_then(parse_number),
+            tags: format.tags,
+            streams,
+        })
+    }
+
+    /// Duration of the file in milliseconds. Falls back to the longest stream when the container
+    /// does not store the duration.
+    pub fn duration(&self) -> Option<u64> {
+        self.duration_ms.or_else(|| self.streams.iter().filter_map(|s| s.duration_ms).max())
+    }
+
+    pub fn video_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(|s| s.kind == StreamKind::Video)
+    }
+
+    pub fn audio_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(|s| s.kind == StreamKind::Audio)
+    }
+
+    pub fn subtitle_streams(&self) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(|s| s.kind == StreamKind::Subtitle)
+    }
+
+    pub fn first_video(&self) -> Option<&StreamInfo> {
+        self.video_streams().next()
+    }
+
+    pub fn first_audio(&self) -> Option<&StreamInfo> {
+        self.audio_streams().next()
+    }
+}
+
+impl StreamInfo {
+    /// Frame rate of a video stream, prefers the average frame rate.
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.avg_frame_rate.or(self.r_frame_rate)
+    }
+
+    /// Guess whether the frame rate is variable, the average frame rate then differs from the base one
+    pub fn is_variable_frame_rate(&self) -> bool {
+        match (self.avg_frame_rate, self.r_frame_rate) {
+            (Some(average), Some(base)) => (average - base).abs() > base * 0.01,
+            _ => false,
+        }
+    }
+
+    /// Width and height of the picture as it is displayed (and seen by the ffmpeg filters), with the rotation applied
+    pub fn display_size(&self) -> Option<(u32, u32)> {
+        let (width, height) = (self.width?, self.height?);
+        if self.rotation.rem_euclid(180) == 90 {
+            Some((height, width))
+        } else {
+            Some((width, height))
+        }
+    }
+
+    pub fn language(&self) -> Option<&str> {
+        self.tags.get("language").map(String::as_str)
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.tags.get("title").map(String::as_str)
+    }
+}
+
+//
+// Raw ffprobe output. ffprobe prints most of the numbers as strings (and "N/A" when unknown),
+// so everything is kept as a string here and parsed later.
+//
+
+#[derive(Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    format: Option<FfprobeFormat>,
+}
+
+#[derive(Deserialize, Default)]
+struct FfprobeFormat {
+    format_name: Option<String>,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    size: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeStream {
+    index: usize,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    codec_long_name: Option<String>,
+    bit_rate: Option<String>,
+    duration: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    channels: Option<u32>,
+    sample_rate: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+    #[serde(default)]
+    side_data_list: Vec<FfprobeSideData>,
+}
+
+#[derive(Deserialize)]
+struct FfprobeSideData {
+    rotation: Option<f64>,
+}
+
+impl From<FfprobeStream> for StreamInfo {
+    fn from(stream: FfprobeStream) -> Self {
+        let kind = match stream.codec_type.as_deref() {
+            Some("video")      => StreamKind::Video,
+            Some("audio")      => StreamKind::Audio,
+            Some("subtitle")   => StreamKind::Subtitle,
+            Some("data")       => StreamKind::Data,
+            Some("attachment") => StreamKind::Attachment,
+            _                  => StreamKind::Unknown,
+        };
+
+        // Newer ffprobe prints the rotation in the display matrix side data, the older one in the tags
+        let rotation = stream.side_data_list.iter().find_map(|side_data| side_data.rotation)
+            .or_else(|| stream.tags.get("rotate").and_then(|rotate| parse_number(rotate)))
+            .map_or(0, |rotation| rotation.round() as i32);
+
+        Self {
+            index: stream.index,
+            kind,
+            codec_name: stream.codec_name.unwrap_or_default(),
+            codec_long_name: stream.codec_long_name.unwrap_or_default(),
+            bit_rate: stream.bit_rate.as_deref().and_then(parse_number),
+            duration_ms: stream.duration.as_deref().and_then(parse_seconds_to_ms),
+            width: stream.width,
+            height: stream.height,
+            avg_frame_rate: stream.avg_frame_rate.as_deref().and_then(parse_rational),
+            r_frame_rate: stream.r_frame_rate.as_deref().and_then(parse_rational),
+            rotation,
+            channels: stream.channels,
+            sample_rate: stream.sample_rate.as_deref().and_then(parse_number),
+            tags: stream.tags,
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str) -> Option<T> {
+    value.trim().parse().ok()
+}
+
+/// Parses "123.456000" seconds into milliseconds
+fn parse_seconds_to_ms(value: &str) -> Option<u64> {
+    let seconds: f64 = value.trim().parse().ok()?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    Some((seconds * 1000.0).round() as u64)
+}
+
+/// Parses frame rates such as "30000/1001". "0/0" is printed for streams without a frame rate.
+fn parse_rational(value: &str) -> Option<f64> {
+    let (num, den) = value.trim().split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    if num == 0.0 || den == 0.0 {
+        return None;
+    }
+    Some(num / den)
+}
@@ -2,11 +2,7 @@ use std::time::Duration;
 
 use egui_toast::{Toast, ToastKind, ToastOptions, Toasts};
 
-#[allow(dead_code)]
-pub struct MessageLog {
-    pub text: &'static str,
-    pub kind: ToastKind,
-}
+use crate::engine::{MessageLog, MessageKind};
 
 pub trait LogKind {
     fn success(&mut self, message: &'static str);
@@ -25,6 +21,22 @@ fn default_options() -> ToastOptions {
     return options;
 }
 
+/// Turns the message reported by a job into a popup
+pub fn message_toast(log: MessageLog) -> Toast {
+    let kind = match log.kind {
+        MessageKind::Info    => ToastKind::Info,
+        MessageKind::Success => ToastKind::Success,
+        MessageKind::Warning => ToastKind::Warning,
+        MessageKind::Error   => ToastKind::Error,
+    };
+
+    Toast {
+        text: log.text.into(),
+        kind,
+        options: ToastOptions::default(),
+    }
+}
+
 impl LogKind for Toasts {
     fn success(&mut self, message: &'static str) {
         let log = Toast {
@@ -0,0 +1,222 @@
+use std::{io::BufRead, time::Duration};
+
+/// A single block of the key=value pairs printed by "ffmpeg -progress <url>". The block always
+/// ends with a "progress=continue" or "progress=end" line. Values ffmpeg reports as "N/A" are None.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FfmpegProgress {
+    /// Position of the encoder in the output media
+    pub out_time: Duration,
+    /// Number of frames encoded so far
+    pub frame: Option<u64>,
+    pub fps: Option<f32>,
+    /// Current bitrate in kbits/s
+    pub bitrate: Option<f32>,
+    /// Size of the output written so far in bytes
+    pub total_size: Option<u64>,
+    /// Encoding speed relative to the playback speed ("speed=2.5x" -> 2.5)
+    pub speed: Option<f32>,
+    /// Set on the last block, when ffmpeg printed "progress=end"
+    pub is_end: bool,
+}
+
+impl FfmpegProgress {
+    /// Applies a single "key=value" line to the block. Returns true when the line ends the block.
+    pub fn apply_line(&mut self, line: &str) -> bool {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            return false;
+        };
+        let value = value.trim();
+
+        match key.trim() {
+            "frame"      => self.frame = value.parse().ok(),
+            "fps"        => self.fps = value.parse().ok(),
+            "bitrate"    => self.bitrate = value.trim_end_matches("kbits/s").trim().parse().ok(),
+            "total_size" => self.total_size = value.parse().ok(),
+            "speed"      => self.speed = value.trim_end_matches('x').trim().parse().ok(),
+            // Both of these are in microseconds, despite the name of the "out_time_ms" (ffmpeg bug)
+            "out_time_us" | "out_time_ms" => {
+                if let Ok(us) = value.parse::<i64>() {
+                    self.out_time = Duration::from_micros(us.max(0) as u64);
+                }
+            }
+            "out_time" => {
+                if let Some(out_time) = parse_timestamp(value) {
+                    self.out_time = out_time;
+                }
+            }
+            "progress" => {
+                self.is_end = value == "end";
+                return true;
+            }
+            _ => {}
+        }
+
+        false
+    }
+}
+
+/// Reads the "-progress" output of ffmpeg line by line and yields each completed block.
+/// Lines are buffered, so key=value pairs split between two reads are never lost.
+pub struct ProgressReader<R> {
+    reader: R,
+    line: String,
+    current: FfmpegProgress,
+}
+
+impl<R: BufRead> ProgressReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            current: FfmpegProgress::default(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ProgressReader<R> {
+    type Item = FfmpegProgress;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) | Err(_) => return None,
+                Ok(_) => {}
+            }
+
+            if self.current.apply_line(&self.line) {
+                // Values not present in the next block are carried over from this one
+                let block = self.current.clone();
+                self.current.is_end = false;
+                return Some(block);
+            }
+        }
+    }
+}
+
+/// Parses "HH:MM:SS.micro" timestamps. ffmpeg prints negative ones at the start of some encodes.
+fn parse_timestamp(value: &str) -> Option<Duration> {
+    let mut parts = value.split(':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    if parts.next().is_some() || !seconds.is_finite() || seconds < 0.0 {
+        return None;
+    }
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// Formats the duration as "HH:MM:SS".
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+/// Template passed to "yt-dlp --progress-template", every download progress line is printed as:
+///     compressor-progress <status> <downloaded> <total> <estimate> <speed> <eta> <fragment> <fragments> <playlist index> <playlist size> <filename>
+/// The filename is the last one since it can contain spaces. Missing values are printed as "NA".
+pub const YTDLP_PROGRESS_TEMPLATE: &str = concat!(
+    "download:compressor-progress ",
+    "%(progress.status)s %(progress.downloaded_bytes)s %(progress.total_bytes)s %(progress.total_bytes_estimate)s ",
+    "%(progress.speed)s %(progress.eta)s %(progress.fragment_index)s %(progress.fragment_count)s ",
+    "%(info.playlist_index)s %(info.n_entries)s %(progress.filename)s",
+);
+
+const YTDLP_PROGRESS_PREFIX: &str = "compressor-progress ";
+
+/// A single progress line printed by yt-dlp with the YTDLP_PROGRESS_TEMPLATE.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DownloadProgress {
+    /// Set when yt-dlp reported the "finished" status for the current file
+    pub is_finished: bool,
+    pub downloaded_bytes: u64,
+    /// Total size of the current file, or its estimate when yt-dlp does not know the exact size
+    pub total_bytes: Option<u64>,
+    /// Download speed in bytes per second
+    pub speed: Option<f64>,
+    pub eta: Option<Duration>,
+    /// Index (starting from 1) and count of fragments for fragmented downloads (HLS, DASH)
+    pub fragment_index: Option<u64>,
+    pub fragment_count: Option<u64>,
+    /// Index (starting from 1) and size of the playlist for multi-file downloads
+    pub playlist_index: Option<u64>,
+    pub playlist_count: Option<u64>,
+    /// Name of the file currently being downloaded
+    pub filename: String,
+}
+
+impl DownloadProgress {
+    /// Parses a line printed with YTDLP_PROGRESS_TEMPLATE. Returns None for any other output.
+    pub fn parse_line(line: &str) -> Option<Self> {
+        let line = line.trim_end_matches(['\r', '\n']);
+        let mut fields = line.strip_prefix(YTDLP_PROGRESS_PREFIX)?.splitn(11, ' ');
+
+        let status = fields.next()?;
+        let downloaded_bytes = parse_ytdlp_number(fields.next()?);
+        let total_bytes = parse_ytdlp_number(fields.next()?);
+        let total_bytes_estimate = parse_ytdlp_number(fields.next()?);
+        let speed = parse_ytdlp_number(fields.next()?);
+        let eta = parse_ytdlp_number(fields.next()?);
+        let fragment_index = parse_ytdlp_number(fields.next()?);
+        let fragment_count = parse_ytdlp_number(fields.next()?);
+        let playlist_index = parse_ytdlp_number(fields.next()?);
+        let playlist_count = parse_ytdlp_number(fields.next()?);
+        let filename = fields.next().unwrap_or_default();
+
+        Some(Self {
+            is_finished: status == "finished",
+            downloaded_bytes: downloaded_bytes.unwrap_or(0.0) as u64,
+            total_bytes: total_bytes.or(total_bytes_estimate).map(|bytes| bytes as u64),
+            speed,
+            eta: eta.map(Duration::from_secs_f64),
+            fragment_index: fragment_index.map(|index| index as u64),
+            fragment_count: fragment_count.map(|count| count as u64),
+            playlist_index: playlist_index.map(|index| index as u64),
+            playlist_count: playlist_count.map(|count| count as u64),
+            filename: String::from(filename),
+        })
+    }
+
+    /// Progress of the current file, value between 0.0 and 1.0
+    pub fn file_fraction(&self) -> Option<f32> {
+        if self.is_finished {
+            return Some(1.0);
+        }
+
+        if let Some(total) = self.total_bytes.filter(|total| *total > 0) {
+            return Some((self.downloaded_bytes as f32 / total as f32).clamp(0.0, 1.0));
+        }
+
+        match (self.fragment_index, self.fragment_count) {
+            (Some(index), Some(count)) if count > 0 => Some((index as f32 / count as f32).clamp(0.0, 1.0)),
+            _ => None,
+        }
+    }
+
+    /// Progress of the whole download including the other files of the playlist, value between 0.0 and 1.0
+    pub fn fraction(&self) -> Option<f32> {
+        let file_fraction = self.file_fraction()?;
+        match (self.playlist_index, self.playlist_count) {
+            (Some(index), Some(count)) if count > 0 && index > 0 => {
+                Some(((index - 1) as f32 + file_fraction) / count as f32)
+            }
+            _ => Some(file_fraction),
+        }
+    }
+}
+
+fn parse_ytdlp_number(value: &str) -> Option<f64> {
+    value.parse::<f64>().ok().filter(|number| number.is_finite() && *number >= 0.0)
+}
+
+/// Formats the number of bytes, for example: "12.50 MiB"
+pub fn format_size(bytes: f64) -> String {
+    const UNITS: &[&str] = &[ "B", "KiB", "MiB", "GiB", "TiB" ];
+    let mut size = bytes;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.2} {}", UNITS[unit])
+}
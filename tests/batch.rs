@@ -0,0 +1,54 @@
+use std::path::{Path, PathBuf};
+
+use compressor::engine::batch::{batch_output_path, collect_media_files, default_batch_output_dir, parse_extensions};
+
+/// Empty directory in the temporary directory, unique for the test
+fn test_dir(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("compressor-batch-{}-{name}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    path
+}
+
+fn create_files(dir: &Path, files: &[&str]) {
+    for file in files {
+        let path = dir.join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"").unwrap();
+    }
+}
+
+#[test]
+fn output_keeps_the_relative_path_of_the_input() {
+    let output = batch_output_path(Path::new("/videos"), Path::new("/converted"), Path::new("/videos/trip/day 1.MKV"), "mp4");
+    assert_eq!(output, Some(PathBuf::from("/converted/trip/day 1.mp4")));
+
+    // Files outside of the input directory have no relative path
+    assert_eq!(batch_output_path(Path::new("/videos"), Path::new("/converted"), Path::new("/music/a.mp3"), "mp3"), None);
+
+    assert_eq!(default_batch_output_dir(Path::new("/videos")), Some(PathBuf::from("/videos-new")));
+
+    // The current directory gets its name from the absolute path
+    let current = std::env::current_dir().unwrap();
+    let expected = format!("{}-new", current.file_name().unwrap().to_string_lossy());
+    assert_eq!(default_batch_output_dir(Path::new(".")), Some(current.with_file_name(expected)));
+}
+
+#[test]
+fn collects_matching_files_and_skips_the_output_directory() {
+    let dir = test_dir("collect");
+    create_files(&dir, &[ "b.mp4", "a.MKV", "notes.txt", "trip/c.mp4", "out/a-new.mp4" ]);
+
+    let extensions = parse_extensions("mp4, .mkv");
+    let files = collect_media_files(&dir, &extensions, false, &dir.join("out"));
+    assert_eq!(files, vec![ dir.join("a.MKV"), dir.join("b.mp4") ]);
+
+    let files = collect_media_files(&dir, &extensions, true, &dir.join("out"));
+    assert_eq!(files, vec![ dir.join("a.MKV"), dir.join("b.mp4"), dir.join("trip/c.mp4") ]);
+
+    // The same directory written differently is skipped as well
+    let files = collect_media_files(&dir, &extensions, true, &dir.join("trip/../out/."));
+    assert!(!files.contains(&dir.join("out/a-new.mp4")));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
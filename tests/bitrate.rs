@@ -0,0 +1,82 @@
+use compressor::engine::Quality;
+use compressor::engine::bitrate::{
+    quality_bitrate, source_video_bitrate, split_bitrate, TargetBitrates, AAC_BITRATE_RANGE, FALLBACK_AUDIO_BITRATE,
+    MP3_BITRATE_RANGE, QUALITY_VIDEO_BITRATE_RANGE,
+};
+use compressor::media_info::MediaInfo;
+
+#[test]
+fn splits_by_the_ratio_after_the_overhead() {
+    // 10 MB over 100 seconds is 800 kbit/s, 784 kbit/s without the container overhead
+    let bitrates = split_bitrate(10.0, 100_000, 6.0, true, true, AAC_BITRATE_RANGE).unwrap();
+    assert_eq!(bitrates, TargetBitrates { video: Some(672), audio: Some(112) });
+}
+
+#[test]
+fn clamps_the_audio_bitrate() {
+    // The audio would get 356 kbit/s
+    let bitrates = split_bitrate(5.0, 10_000, 10.0, true, true, AAC_BITRATE_RANGE).unwrap();
+    assert_eq!(bitrates, TargetBitrates { video: Some(3600), audio: Some(320) });
+
+    // The audio would get 7 kbit/s
+    let bitrates = split_bitrate(10.0, 100_000, 100.0, true, true, AAC_BITRATE_RANGE).unwrap();
+    assert_eq!(bitrates, TargetBitrates { video: Some(752), audio: Some(32) });
+}
+
+#[test]
+fn gives_everything_to_the_only_stream() {
+    let bitrates = split_bitrate(10.0, 100_000, 10.0, true, false, AAC_BITRATE_RANGE).unwrap();
+    assert_eq!(bitrates, TargetBitrates { video: Some(784), audio: None });
+
+    let bitrates = split_bitrate(1.0, 100_000, 10.0, false, true, MP3_BITRATE_RANGE).unwrap();
+    assert_eq!(bitrates, TargetBitrates { video: None, audio: Some(78) });
+}
+
+#[test]
+fn rejects_sizes_too_small_for_the_media() {
+    assert!(split_bitrate(0.1, 600_000, 10.0, true, true, AAC_BITRATE_RANGE).is_err());
+    assert!(split_bitrate(0.01, 600_000, 10.0, false, true, MP3_BITRATE_RANGE).is_err());
+    assert!(split_bitrate(0.0, 10_000, 10.0, true, true, AAC_BITRATE_RANGE).is_err());
+    assert!(split_bitrate(10.0, 0, 10.0, true, true, AAC_BITRATE_RANGE).is_err());
+}
+
+#[test]
+fn formats_the_bitrates() {
+    let bitrates = TargetBitrates { video: Some(3600), audio: Some(320) };
+    assert_eq!(bitrates.to_string(), "Video: 3600 kbit/s, Audio: 320 kbit/s");
+
+    let bitrates = TargetBitrates { video: None, audio: Some(128) };
+    assert_eq!(bitrates.to_string(), "Audio: 128 kbit/s");
+}
+
+#[test]
+fn quality_bitrates_are_relative_to_the_source() {
+    let range = QUALITY_VIDEO_BITRATE_RANGE;
+    assert_eq!(quality_bitrate(Quality::Good, Some(20_000), 0, range.clone()), 18_000);
+    assert_eq!(quality_bitrate(Quality::Bad, Some(1000), 0, range.clone()), 300);
+    assert_eq!(quality_bitrate(Quality::Medium, None, 2000, range.clone()), 1200);
+}
+
+#[test]
+fn quality_bitrates_stay_between_the_floor_and_the_source() {
+    let range = QUALITY_VIDEO_BITRATE_RANGE;
+    // 10% would drop below the floor
+    assert_eq!(quality_bitrate(Quality::Poop, Some(500), 0, range.clone()), 100);
+    // The floor does not inflate sources smaller than itself
+    assert_eq!(quality_bitrate(Quality::Original, Some(80), 0, range.clone()), 100);
+    assert_eq!(quality_bitrate(Quality::Poop, Some(200_000), 0, range), 20_000);
+    assert_eq!(quality_bitrate(Quality::Original, Some(500), 0, AAC_BITRATE_RANGE), 320);
+}
+
+#[test]
+fn video_bitrate_is_estimated_without_the_audio() {
+    let probe = r#"{
+        "streams": [
+            { "index": 0, "codec_type": "video", "codec_name": "vp9" },
+            { "index": 1, "codec_type": "audio", "codec_name": "opus" }
+        ],
+        "format": { "format_name": "matroska,webm", "duration": "10.0", "bit_rate": "3128000" }
+    }"#;
+    let info = MediaInfo::from_ffprobe_json(probe).unwrap();
+    assert_eq!(source_video_bitrate(&info), Some(3128 - FALLBACK_AUDIO_BITRATE));
+}
@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use compressor::cli::parse_args;
+use compressor::engine::*;
+use compressor::engine::animation::AnimationFormat;
+use compressor::engine::codec::{AudioCodec, VideoCodec};
+use compressor::engine::scale::{ScaleFit, ScaleSize};
+
+/// Empty input file in the temporary directory, unique for the test
+fn input_file(name: &str) -> String {
+    let path: PathBuf = std::env::temp_dir().join(format!("compressor-cli-{}-{name}", std::process::id()));
+    std::fs::write(&path, b"").unwrap();
+    path.to_string_lossy().to_string()
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn parse_error(arguments: &[&str]) -> &'static str {
+    parse_args(&args(arguments)).err().expect("the arguments should be rejected")
+}
+
+/// The single convertion job created from the arguments
+fn parse_convertion(arguments: &[&str]) -> ConvertionJob {
+    let Ok(mut jobs) = parse_args(&args(arguments)) else {
+        panic!("the arguments should be accepted");
+    };
+    assert_eq!(jobs.len(), 1);
+
+    let CompressorJob::Convert(job) = jobs.remove(0) else {
+        panic!("expected a convertion job");
+    };
+    *job
+}
+
+#[test]
+fn modes_are_selected_by_their_names() {
+    let input = input_file("modes.mkv");
+
+    assert!(matches!(parse_convertion(&[ "audio", &input ]).mode, AppMode::Audio));
+    assert!(matches!(parse_convertion(&[ "video", &input ]).mode, AppMode::Video));
+    assert!(matches!(parse_convertion(&[ "thumbnail", &input ]).mode, AppMode::Thumbnail));
+
+    let gif = parse_convertion(&[ "gif", &input ]);
+    assert!(matches!(gif.mode, AppMode::Animation));
+    assert_eq!(gif.animation_options.format, AnimationFormat::Gif);
+
+    let webp = parse_convertion(&[ "webp", &input ]);
+    assert_eq!(webp.animation_options.format, AnimationFormat::Webp);
+
+    // The output goes next to the input by default
+    let video = parse_convertion(&[ "video", &input ]);
+    assert_eq!(video.output_file, input.replace("modes.mkv", "modes-new.mp4"));
+
+    let Ok(jobs) = parse_args(&args(&[ "download", "https://example.com/video", "-o", "clip.mp4" ])) else {
+        panic!("the download should be accepted");
+    };
+    let [CompressorJob::Download(download)] = &jobs[..] else {
+        panic!("expected a download job");
+    };
+    assert_eq!(download.input_link, "https://example.com/video");
+    assert_eq!(download.output_file, "clip.mp4");
+
+    assert_eq!(parse_error(&[]), "No mode was provided.");
+    assert_eq!(parse_error(&[ "compress", &input ]), "Unknown mode. Expected one of: audio, video, download, thumbnail, gif, webp.");
+}
+
+#[test]
+fn size_options_are_read() {
+    let input = input_file("size.mp4");
+
+    let job = parse_convertion(&[ "video", &input, "--size", "8.5", "--ratio", "5", "--verify-size" ]);
+    assert_eq!(job.output_file_size, Some(8.5));
+    assert_eq!(job.bitrate_ratio, 5.0);
+    assert!(job.verify_output_size);
+
+    let job = parse_convertion(&[ "video", &input, "-s", "20" ]);
+    assert_eq!(job.output_file_size, Some(20.0));
+    assert_eq!(job.bitrate_ratio, 10.0);
+    assert!(!job.verify_output_size);
+
+    assert_eq!(parse_error(&[ "video", &input, "--size", "big" ]), "Failed to read the output file size.");
+    assert_eq!(parse_error(&[ "video", &input, "--ratio", "x" ]), "Failed to read the bitrate ratio.");
+    assert_eq!(parse_error(&[ "video", &input, "--ratio", "30" ]), "Bitrate ratio must be between 1 and 20.");
+    assert_eq!(
+        parse_error(&[ "thumbnail", &input, "--size", "1" ]),
+        "The size of the thumbnail can not be selected, --size and --verify-size can not be used.",
+    );
+}
+
+#[test]
+fn encoding_options_are_read() {
+    let input = input_file("encoding.mp4");
+
+    let job = parse_convertion(&[
+        "video", &input, "-c", "vp9", "-p", "slow", "-v", "bad", "-a", "Good", "--rate-mode", "bitrate",
+        "-r", "1280x720", "--fit", "pad", "--upscale", "-o", "out",
+    ]);
+    assert_eq!(job.video_codec, VideoCodec::Vp9);
+    assert_eq!(job.preset, 1);
+    assert_eq!(job.video_quality as usize, Quality::Bad as usize);
+    assert_eq!(job.audio_quality as usize, Quality::Good as usize);
+    assert_eq!(job.video_rate_mode, VideoRateMode::BitrateFirst);
+    assert_eq!(job.output_file, "out.webm");
+
+    let scaling = job.scaling.unwrap();
+    assert_eq!(scaling.size, ScaleSize::Box { width: 1280, height: 720 });
+    assert_eq!(scaling.fit, ScaleFit::Pad);
+    assert!(scaling.upscale);
+
+    let job = parse_convertion(&[ "audio", &input, "--audio-codec", "opus", "-r", "480p" ]);
+    assert_eq!(job.audio_codec, AudioCodec::Opus);
+    assert_eq!(job.scaling.unwrap().size, ScaleSize::ShortSide(480));
+
+    assert_eq!(parse_error(&[ "video", &input, "-c", "mpeg2" ]), "Unknown codec. Expected one of: h264, h265, vp9, av1, svt-av1.");
+    assert_eq!(parse_error(&[ "video", &input, "-v", "great" ]), "Unknown quality. Expected one of: original, good, medium, bad, poop.");
+    assert_eq!(
+        parse_error(&[ "video", &input, "-r", "big" ]),
+        "Failed to read the resolution. Expected the short side (720p) or the size (1280x720).",
+    );
+}
+
+#[test]
+fn trim_options_are_read() {
+    let input = input_file("trim.mp4");
+
+    let job = parse_convertion(&[ "video", &input, "--start", "1:00", "--duration", "30" ]);
+    assert_eq!(job.trim.start, Some(60_000));
+    assert_eq!(job.trim.end, Some(90_000));
+
+    let error = parse_error(&[ "video", &input, "--end", "2:00", "--duration", "30" ]);
+    assert_eq!(error, "Only one of --end and --duration can be provided.");
+    assert_eq!(parse_error(&[ "video", &input, "--start", "soon" ]), "Failed to read the --start time.");
+}
+
+#[test]
+fn missing_values_and_unknown_options_are_rejected() {
+    let input = input_file("invalid.mp4");
+
+    assert_eq!(parse_error(&[ "video", &input, "--size" ]), "Missing value for --size.");
+    assert_eq!(parse_error(&[ "video", &input, "-o" ]), "Missing value for --output.");
+    assert_eq!(parse_error(&[ "video", &input, "--ratio" ]), "Missing value for --ratio.");
+    assert_eq!(parse_error(&[ "video", &input, "--resolution" ]), "Missing value for --resolution.");
+
+    assert_eq!(parse_error(&[ "video", &input, "--crf", "23" ]), "Unknown option.");
+    assert_eq!(parse_error(&[ "video", &input, &input ]), "Only one input can be provided.");
+    assert_eq!(parse_error(&[ "video", "--size", "8" ]), "No input was provided.");
+    assert_eq!(parse_error(&[ "video", "missing-input.mp4" ]), "Input path is incorrect, file does not exist.");
+}
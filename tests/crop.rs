@@ -0,0 +1,33 @@
+use compressor::engine::crop::CropRect;
+
+#[test]
+fn reads_the_last_crop_of_the_cropdetect_log() {
+    let log = "\
+Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'input.mp4':
+[Parsed_cropdetect_0 @ 0x5581] x1:0 x2:1919 y1:142 y2:937 w:1920 h:796 x:0 y:142 pts:512 t:0.033 limit:0.094 crop=1920:796:0:142
+[Parsed_cropdetect_0 @ 0x5581] x1:0 x2:1919 y1:140 y2:939 w:1920 h:800 x:0 y:140 pts:1024 t:0.067 limit:0.094 crop=1920:800:0:140
+[out#0/null @ 0x5590] video:42kB audio:0kB subtitle:0kB other streams:0kB global headers:0kB muxing overhead: unknown
+";
+    let rect = CropRect::from_cropdetect_output(log).unwrap();
+    assert_eq!(rect, CropRect { width: 1920, height: 800, x: 0, y: 140 });
+    assert_eq!(rect.filter(), "crop=1920:800:0:140");
+
+    // The black frames get a negative size
+    let black = "[Parsed_cropdetect_0 @ 0x5581] x1:1919 x2:0 y1:1079 y2:0 crop=-1920:-1072:1928:1080";
+    assert_eq!(CropRect::from_cropdetect_output(black), None);
+    assert_eq!(CropRect::from_cropdetect_output("Conversion failed!"), None);
+}
+
+#[test]
+fn crops_of_the_parts_are_joined() {
+    let letterbox = CropRect { width: 1920, height: 800, x: 0, y: 140 };
+    let pillarbox = CropRect { width: 1440, height: 1080, x: 240, y: 0 };
+
+    // A bright scene in any of the parts keeps the bars around it
+    assert_eq!(letterbox.union(&pillarbox), CropRect { width: 1920, height: 1080, x: 0, y: 0 });
+    assert!(letterbox.union(&pillarbox).is_full(1920, 1080));
+    assert!(!letterbox.is_full(1920, 1080));
+
+    assert!(letterbox.fits(1920, 1080));
+    assert!(!letterbox.fits(1280, 720));
+}
@@ -0,0 +1,1348 @@
+use std::{
+    collections::VecDeque, io::{Cursor, Read}, path::{Path, PathBuf}, process::Command,
+    sync::{Arc, Mutex, atomic::{AtomicUsize, Ordering}},
+    time::{Duration, Instant},
+};
+
+use compressor::engine::*;
+use compressor::engine::animation::{AnimationFormat, AnimationOptions};
+use compressor::engine::batch::batch_jobs;
+use compressor::engine::bitrate::MAX_SIZE_ATTEMPTS;
+use compressor::engine::framerate::{FrameRate, FrameRateMethod, FrameRateTarget};
+use compressor::engine::frames::{extract_frame, frame_size, thumbnail_times};
+use compressor::engine::loudness::{Loudnorm, LoudnessMeasurement};
+use compressor::engine::codec::{AudioCodec, VideoCodec, DEFAULT_FLAC_COMPRESSION};
+use compressor::engine::crop::{Crop, CropRect, CROP_SAMPLES};
+use compressor::engine::scale::Scaling;
+use compressor::engine::subtitles::{BurnIn, SubtitleTracks, Subtitles};
+use compressor::engine::thumbnail::{ImageFormat, ThumbnailKind, ThumbnailOptions};
+use compressor::engine::trim::{CutMode, Trim};
+use compressor::engine::runner::{probe_media, ProcessRunner, RunningProcess};
+use compressor::media_info::MediaInfo;
+
+const FFPROBE_OUTPUT: &str = r#"{
+    "streams": [
+        { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720, "avg_frame_rate": "30/1" },
+        { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2, "sample_rate": "48000" }
+    ],
+    "format": { "format_name": "mov,mp4,m4a,3gp,3g2,mj2", "duration": "10.000000", "bit_rate": "1000000" }
+}"#;
+
+const FFPROBE_SUBTITLES_OUTPUT: &str = r#"{
+    "streams": [
+        { "index": 0, "codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720 },
+        { "index": 1, "codec_type": "audio", "codec_name": "aac", "channels": 2, "sample_rate": "48000" },
+        { "index": 2, "codec_type": "subtitle", "codec_name": "subrip", "tags": { "language": "pol" } },
+        { "index": 3, "codec_type": "subtitle", "codec_name": "hdmv_pgs_subtitle", "tags": { "language": "eng" } }
+    ],
+    "format": { "format_name": "matroska,webm", "duration": "10.000000" }
+}"#;
+
+const FFMPEG_OUTPUT: &str = "\
+fps=30.00
+out_time_us=2500000
+speed=2.00x
+progress=continue
+fps=30.00
+out_time_us=5000000
+speed=2.00x
+progress=continue
+fps=30.00
+out_time_us=10000000
+speed=2.00x
+progress=end
+";
+
+const YTDLP_OUTPUT: &str = "\
+[youtube] Extracting URL: https://example.com/watch
+compressor-progress downloading 1048576 4194304 NA 524288.0 6 NA NA NA NA /tmp/video.mp4
+compressor-progress downloading 2097152 4194304 NA 524288.0 4 NA NA NA NA /tmp/video.mp4
+compressor-progress finished 4194304 4194304 NA NA NA NA NA NA NA /tmp/video.mp4
+";
+
+// Log of the measuring pass of the loudness normalization, the summary is printed by the loudnorm filter
+const LOUDNORM_LOG: &str = r#"[Parsed_loudnorm_0 @ 0x5581] 
+{
+	"input_i" : "-27.61",
+	"input_tp" : "-4.47",
+	"input_lra" : "18.06",
+	"input_thresh" : "-39.20",
+	"output_i" : "-23.10",
+	"output_tp" : "-1.00",
+	"output_lra" : "9.20",
+	"output_thresh" : "-34.53",
+	"normalization_type" : "dynamic",
+	"target_offset" : "0.10"
+}
+"#;
+
+// Log of the crop detection of a letterboxed 1280x720 video, the last line covers the whole part
+const CROPDETECT_LOG: &str = "\
+[Parsed_cropdetect_0 @ 0x5581] x1:0 x2:1279 y1:90 y2:629 w:1280 h:540 x:0 y:90 pts:512 t:0.033 limit:0.094 crop=1280:540:0:90
+[Parsed_cropdetect_0 @ 0x5581] x1:0 x2:1279 y1:88 y2:631 w:1280 h:544 x:0 y:88 pts:1024 t:0.067 limit:0.094 crop=1280:544:0:88
+";
+
+// Log of the crop detection of a part with nothing but the black frames
+const CROPDETECT_BLACK_LOG: &str = "\
+[Parsed_cropdetect_0 @ 0x5581] x1:1279 x2:0 y1:719 y2:0 w:-1278 h:-718 x:1280 y:720 pts:512 t:0.033 limit:0.094 crop=-1280:-720:1280:720
+";
+
+/// A recorded run of a program, replayed by the ScriptedRunner
+#[derive(Clone)]
+struct Script {
+    program: &'static str,
+    output: &'static str,
+    /// Printed to the standard error
+    log: Option<&'static str>,
+    success: bool,
+    /// The process never exits on its own, it has to be killed
+    hangs: bool,
+    /// Size of the file written to the path in the last argument, as ffmpeg does with the output file
+    writes: Option<usize>,
+    /// Size of the file written into the directory of the "-o" template, as yt-dlp does with the download
+    downloads: Option<usize>,
+    /// The standard output can not be taken
+    no_stdout: bool,
+}
+
+impl Script {
+    fn new(program: &'static str, output: &'static str) -> Self {
+        Self { program, output, log: None, success: true, hangs: false, writes: None, downloads: None, no_stdout: false }
+    }
+
+    fn logging(mut self, log: &'static str) -> Self {
+        self.log = Some(log);
+        self
+    }
+
+    fn writing(mut self, size: usize) -> Self {
+        self.writes = Some(size);
+        self
+    }
+
+    fn downloading(mut self, size: usize) -> Self {
+        self.downloads = Some(size);
+        self
+    }
+
+    fn without_stdout(mut self) -> Self {
+        self.no_stdout = true;
+        self
+    }
+
+    fn failing(mut self) -> Self {
+        self.success = false;
+        self
+    }
+
+    fn hanging(mut self) -> Self {
+        self.hangs = true;
+        self
+    }
+}
+
+/// Replays the scripts in order, one for every spawned process, and records the started commands
+struct ScriptedRunner {
+    installed: Vec<&'static str>,
+    scripts: Mutex<VecDeque<Script>>,
+    calls: Mutex<Vec<Vec<String>>>,
+    kills: Arc<AtomicUsize>,
+}
+
+impl ScriptedRunner {
+    fn new(scripts: Vec<Script>) -> Arc<Self> {
+        Arc::new(Self {
+            installed: vec![ "ffmpeg", "ffprobe", "yt-dlp" ],
+            scripts: Mutex::new(scripts.into()),
+            calls: Mutex::new(Vec::new()),
+            kills: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    fn without_programs(scripts: Vec<Script>) -> Arc<Self> {
+        Arc::new(Self {
+            installed: Vec::new(),
+            scripts: Mutex::new(scripts.into()),
+            calls: Mutex::new(Vec::new()),
+            kills: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Program and the arguments of every started process
+    fn calls(&self) -> Vec<Vec<String>> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl ProcessRunner for ScriptedRunner {
+    fn find_program(&self, name: &str) -> Option<String> {
+        self.installed.contains(&name).then(|| format!("/fake/bin/{name}"))
+    }
+
+    fn spawn(&self, command: Command) -> std::io::Result<Box<dyn RunningProcess>> {
+        let mut call = vec![ command.get_program().to_string_lossy().to_string() ];
+        call.extend(command.get_args().map(|arg| arg.to_string_lossy().to_string()));
+        self.calls.lock().unwrap().push(call.clone());
+
+        let Some(script) = self.scripts.lock().unwrap().pop_front() else {
+            return Err(std::io::ErrorKind::NotFound.into());
+        };
+
+        let program = command.get_program().to_string_lossy().to_string();
+        assert!(program.ends_with(script.program), "expected {} to be started, got {program}", script.program);
+
+        if let (Some(size), Some(path)) = (script.writes, command.get_args().last()) {
+            std::fs::write(path, vec![0; size])?;
+        }
+
+        if let (Some(size), Some(directory)) = (script.downloads, download_dir(&call)) {
+            std::fs::write(directory.join("video.mp4"), vec![0; size])?;
+        }
+
+        Ok(Box::new(ScriptedProcess {
+            stdout: (!script.no_stdout).then(|| Cursor::new(script.output.as_bytes())),
+            stderr: script.log.map(|log| Cursor::new(log.as_bytes())),
+            script,
+            killed: false,
+            kills: self.kills.clone(),
+        }))
+    }
+}
+
+struct ScriptedProcess {
+    script: Script,
+    stdout: Option<Cursor<&'static [u8]>>,
+    stderr: Option<Cursor<&'static [u8]>>,
+    killed: bool,
+    kills: Arc<AtomicUsize>,
+}
+
+impl RunningProcess for ScriptedProcess {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        let stdout = self.stdout.take()?;
+        Some(Box::new(stdout))
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        let stderr = self.stderr.take()?;
+        Some(Box::new(stderr))
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<bool>> {
+        if self.killed {
+            return Ok(Some(false));
+        }
+
+        if self.script.hangs {
+            return Ok(None);
+        }
+
+        Ok(Some(self.script.success))
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        self.killed = true;
+        self.kills.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+struct JobResult {
+    status: JobStatus,
+    progress: Vec<WorkProgress>,
+    messages: Vec<MessageLog>,
+}
+
+fn wait_for_job(handle: &JobHandle) -> JobResult {
+    let mut progress = Vec::new();
+    let mut messages = Vec::new();
+
+    let start = Instant::now();
+    loop {
+        assert!(start.elapsed() < Duration::from_secs(10), "the job did not finish in time");
+
+        match handle.wait_event(Duration::from_millis(100)) {
+            Some(JobEvent::Progress(update)) => progress.push(update),
+            Some(JobEvent::Message(message)) => messages.push(message),
+            Some(JobEvent::Finished(status)) => return JobResult { status, progress, messages },
+            None => {}
+        }
+    }
+}
+
+/// Path in the temporary directory, unique for every test
+fn temp_output(name: &str) -> String {
+    let path: PathBuf = std::env::temp_dir().join(format!("compressor-test-{}-{name}", std::process::id()));
+    path.to_string_lossy().to_string()
+}
+
+fn convertion_job(mode: AppMode, output_file: String) -> ConvertionJob {
+    ConvertionJob {
+        input_file: String::from("input.mp4"),
+        output_file,
+        mode,
+        audio_quality: Quality::default(),
+        video_quality: Quality::default(),
+        video_rate_mode: VideoRateMode::default(),
+        scaling: None,
+        crop: Crop::None,
+        frame_rate: FrameRate::default(),
+        preset: 2,
+        video_codec: VideoCodec::default(),
+        audio_codec: AudioCodec::default(),
+        flac_compression: DEFAULT_FLAC_COMPRESSION,
+        output_file_size: None,
+        bitrate_ratio: 10.0,
+        verify_output_size: false,
+        trim: Trim::default(),
+        loudnorm: None,
+        subtitles: Subtitles::default(),
+        advanced_options: ConvertOptions::default(),
+        thumbnail_options: ThumbnailOptions::default(),
+        animation_options: AnimationOptions::default(),
+    }
+}
+
+/// Directory of the output template of the yt-dlp call
+fn download_dir(call: &[String]) -> Option<PathBuf> {
+    let template = call.iter().skip_while(|arg| *arg != "-o").nth(1)?;
+    Some(PathBuf::from(template).parent()?.to_path_buf())
+}
+
+fn has_args(call: &[String], args: &[&str]) -> bool {
+    call.windows(args.len()).any(|window| window.iter().zip(args).all(|(a, b)| a == b))
+}
+
+#[test]
+fn probe_media_reads_ffprobe_output() {
+    let runner = ScriptedRunner::new(vec![ Script::new("ffprobe", FFPROBE_OUTPUT) ]);
+
+    let info = probe_media(runner.as_ref(), "input.mp4").unwrap();
+    assert_eq!(info.duration(), Some(10_000));
+    assert_eq!(info.first_video().unwrap().width, Some(1280));
+    assert_eq!(info.first_audio().unwrap().sample_rate, Some(48000));
+
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 1);
+    assert!(has_args(&calls[0], &[ "-print_format", "json" ]));
+    assert_eq!(calls[0].last().unwrap(), "input.mp4");
+}
+
+#[test]
+fn probe_media_fails_with_failing_ffprobe() {
+    let runner = ScriptedRunner::new(vec![ Script::new("ffprobe", "").failing() ]);
+    assert!(probe_media(runner.as_ref(), "input.mp4").is_none());
+}
+
+#[test]
+fn convertion_reports_progress_until_the_end() {
+    let output = temp_output("progress.mp3");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let job = CompressorJob::Convert(Box::new(convertion_job(AppMode::Audio, output.clone())));
+    let result = wait_for_job(&job.start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert_eq!(result.messages[0].kind, MessageKind::Success);
+
+    let fractions: Vec<f32> = result.progress.iter().map(|update| update.fraction).collect();
+    assert_eq!(fractions, vec![ 0.25, 0.5, 1.0 ]);
+
+    // 5 seconds of the media are left at the speed of 2x
+    assert_eq!(result.progress[1].eta, Some(Duration::from_millis(2500)));
+
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 2);
+    assert!(has_args(&calls[1], &[ "-progress", "pipe:1", "-i", "input.mp4" ]));
+    assert_eq!(calls[1].last().unwrap(), &output);
+
+    // Nothing but the errors of ffmpeg gets to the terminal
+    assert!(has_args(&calls[1], &[ "-nostdin", "-hide_banner", "-nostats", "-loglevel", "error" ]));
+}
+
+#[test]
+fn target_size_runs_two_passes_in_order() {
+    let output = temp_output("two-pass.mp4");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, output.clone());
+    job.output_file_size = Some(5.0);
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    // Each of the passes covers a half of the progress
+    let fractions: Vec<f32> = result.progress.iter().map(|update| update.fraction).collect();
+    assert_eq!(fractions, vec![ 0.125, 0.25, 0.5, 0.625, 0.75, 1.0 ]);
+
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 3);
+    assert!(has_args(&calls[1], &[ "-pass", "1" ]));
+    assert!(has_args(&calls[1], &[ "-an", "-f", "null" ]));
+    assert!(has_args(&calls[2], &[ "-pass", "2" ]));
+    assert!(has_args(&calls[2], &[ "-b:v", "3600K", "-b:a", "320K" ]));
+    assert_eq!(calls[2].last().unwrap(), &output);
+
+    // Both passes share the statistics in the temporary directory, not in the working directory
+    let log_file = pass_log_file(&calls[1]);
+    assert!(log_file.starts_with(std::env::temp_dir()));
+    assert!(has_args(&calls[2], &[ "-passlogfile", &log_file.to_string_lossy() ]));
+    assert!(!Path::new(&format!("{}-0.log", log_file.display())).exists());
+}
+
+fn pass_log_file(call: &[String]) -> PathBuf {
+    let index = call.iter().position(|arg| arg == "-passlogfile").unwrap();
+    PathBuf::from(&call[index + 1])
+}
+
+/// Runs the target-size convertion of the video with the codec and returns the started processes
+fn run_codec_job(codec: VideoCodec, pass_count: usize) -> (ConvertionJob, Vec<Vec<String>>) {
+    let mut scripts = vec![ Script::new("ffprobe", FFPROBE_OUTPUT) ];
+    for _ in 0..pass_count {
+        scripts.push(Script::new("ffmpeg", FFMPEG_OUTPUT));
+    }
+    let runner = ScriptedRunner::new(scripts);
+
+    let mut job = convertion_job(AppMode::Video, temp_output(&format!("codec.{}", codec.container())));
+    job.video_codec = codec;
+    job.output_file_size = Some(5.0);
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job.clone())).start_with(runner.clone()));
+    assert_eq!(result.status, JobStatus::Finished);
+
+    (job, runner.calls())
+}
+
+#[test]
+fn vp9_is_encoded_into_webm_with_its_own_options() {
+    let (job, calls) = run_codec_job(VideoCodec::Vp9, 2);
+
+    assert_eq!(job.output_extension(), "webm");
+    assert_eq!(calls.len(), 3);
+    assert!(has_args(&calls[1], &[ "-c:v", "libvpx-vp9", "-deadline", "good", "-cpu-used", "3" ]));
+    assert!(has_args(&calls[1], &[ "-pass", "1" ]));
+    assert!(has_args(&calls[2], &[ "-c:a", "libopus" ]));
+    assert!(has_args(&calls[2], &[ "-pass", "2" ]));
+    assert!(!calls[2].iter().any(|arg| arg == "-preset"));
+}
+
+#[test]
+fn x265_passes_are_set_through_its_params() {
+    let (job, calls) = run_codec_job(VideoCodec::H265, 2);
+
+    assert_eq!(job.output_extension(), "mp4");
+    assert!(has_args(&calls[1], &[ "-c:v", "libx265", "-preset", "medium" ]));
+    assert!(calls[1].iter().any(|arg| arg.starts_with("pass=1:stats=")));
+    assert!(calls[2].iter().any(|arg| arg.starts_with("pass=2:stats=")));
+    assert!(!calls[2].iter().any(|arg| arg == "-pass" || arg == "-passlogfile"));
+}
+
+#[test]
+fn pass_statistics_are_written_to_the_selected_file() {
+    // The colons of a Windows path would split the options of x265
+    let windows_path = Path::new(r"C:\Temp\compressor-pass");
+    assert_eq!(VideoCodec::H265.pass_args(1, windows_path), vec![ "-x265-params", r"pass=1:stats=C\:\\Temp\\compressor-pass" ]);
+
+    let log_file = Path::new("/tmp/compressor-pass");
+    assert_eq!(VideoCodec::H264.pass_args(2, log_file), vec![ "-pass", "2", "-passlogfile", "/tmp/compressor-pass" ]);
+
+    // The temporary files of a canceled pass are removed as well
+    let files = VideoCodec::H264.pass_log_files(log_file);
+    assert!(files.contains(&PathBuf::from("/tmp/compressor-pass-0.log")));
+    assert!(files.contains(&PathBuf::from("/tmp/compressor-pass-0.log.mbtree.temp")));
+
+    let files = VideoCodec::H265.pass_log_files(log_file);
+    assert!(files.contains(&PathBuf::from("/tmp/compressor-pass")));
+    assert!(files.contains(&PathBuf::from("/tmp/compressor-pass.cutree")));
+}
+
+#[test]
+fn svt_av1_is_encoded_in_a_single_pass() {
+    let (_, calls) = run_codec_job(VideoCodec::SvtAv1, 1);
+
+    assert_eq!(calls.len(), 2);
+    assert!(has_args(&calls[1], &[ "-c:v", "libsvtav1", "-preset", "8" ]));
+    assert!(has_args(&calls[1], &[ "-b:v", "3600K" ]));
+}
+
+#[test]
+fn advanced_crf_of_vp9_disables_the_bitrate_limit() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Advanced, temp_output("crf.webm"));
+    job.video_codec = VideoCodec::Vp9;
+    job.advanced_options.crf = 40;
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert!(has_args(&runner.calls()[1], &[ "-crf", "40", "-b:v", "0" ]));
+}
+
+#[test]
+fn sample_rate_unsupported_by_the_codec_is_rejected() {
+    let mut options = ConvertOptions { video_enabled: false, audio_sample_rate: 96000, ..Default::default() };
+    assert!(options.validate(VideoCodec::H264, AudioCodec::Aac).is_ok());
+
+    // LAME only accepts the MPEG sample rates
+    let error = options.validate(VideoCodec::H264, AudioCodec::Mp3).unwrap_err();
+    assert!(error.starts_with("MP3"));
+
+    options.audio_sample_rate = 44100;
+    assert!(options.validate(VideoCodec::H264, AudioCodec::Mp3).is_ok());
+    let error = options.validate(VideoCodec::H264, AudioCodec::Opus).unwrap_err();
+    assert!(error.starts_with("Opus"));
+}
+
+#[test]
+fn tune_unsupported_by_the_codec_is_rejected() {
+    let mut options = ConvertOptions { tune: "film", ..Default::default() };
+    assert!(options.validate(VideoCodec::H264, AudioCodec::Mp3).is_ok());
+    assert!(options.validate(VideoCodec::Vp9, AudioCodec::Mp3).is_err());
+
+    options.tune = "";
+    options.crf = 60;
+    assert!(options.validate(VideoCodec::H264, AudioCodec::Mp3).is_err());
+    assert!(options.validate(VideoCodec::Av1, AudioCodec::Mp3).is_ok());
+}
+
+#[test]
+fn video_quality_levels_select_crf_or_bitrate() {
+    let run = |rate_mode: VideoRateMode, codec: VideoCodec| {
+        let runner = ScriptedRunner::new(vec![
+            Script::new("ffprobe", FFPROBE_OUTPUT),
+            Script::new("ffmpeg", FFMPEG_OUTPUT),
+        ]);
+
+        let mut job = convertion_job(AppMode::Video, temp_output("rate-mode.mp4"));
+        job.video_quality = Quality::Medium;
+        job.video_rate_mode = rate_mode;
+        job.video_codec = codec;
+        let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+        assert_eq!(result.status, JobStatus::Finished);
+        runner.calls().pop().unwrap()
+    };
+
+    let call = run(VideoRateMode::QualityFirst, VideoCodec::H264);
+    assert!(has_args(&call, &[ "-crf", "28" ]));
+    assert!(!call.iter().any(|arg| arg == "-b:v"));
+
+    let call = run(VideoRateMode::QualityFirst, VideoCodec::Vp9);
+    assert!(has_args(&call, &[ "-crf", "37", "-b:v", "0" ]));
+
+    let call = run(VideoRateMode::BitrateFirst, VideoCodec::H264);
+    // 60% of the 1000 kbit/s of the input without the assumed 128 kbit/s of the audio
+    assert!(has_args(&call, &[ "-b:v", "523K" ]));
+    assert!(!call.iter().any(|arg| arg == "-crf"));
+}
+
+#[test]
+fn trimmed_convertion_seeks_and_measures_the_trimmed_part() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, temp_output("trim.mp4"));
+    job.trim = Trim { start: Some(2000), end: Some(7000), cut_mode: CutMode::Accurate };
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+    // The progress is relative to the 5 kept seconds, not to the 10 seconds of the input
+    assert_eq!(result.progress[0].fraction, 0.5);
+
+    let call = runner.calls().pop().unwrap();
+    assert!(has_args(&call, &[ "-ss", "2.000", "-i", "input.mp4", "-y", "-t", "5.000" ]));
+    assert!(has_args(&call, &[ "-c:v", "libx264" ]));
+}
+
+#[test]
+fn fast_cut_copies_the_streams_into_the_input_container() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Audio, temp_output("cut.mp4"));
+    job.trim = Trim { start: None, end: Some(5000), cut_mode: CutMode::Fast };
+    assert_eq!(job.output_extension(), "mp4");
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job.clone())).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+    let call = runner.calls().pop().unwrap();
+    assert!(has_args(&call, &[ "-vn", "-c", "copy" ]));
+    assert!(!call.iter().any(|arg| arg == "-c:a"));
+
+    // Nothing is encoded, so the size can not be selected
+    job.output_file_size = Some(1.0);
+    let runner = ScriptedRunner::new(vec![ Script::new("ffprobe", FFPROBE_OUTPUT) ]);
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner));
+    assert_eq!(result.status, JobStatus::Failed);
+}
+
+/// Runs the Audio mode convertion with the codec and returns the arguments of ffmpeg
+fn run_audio_job(job: ConvertionJob) -> (JobResult, Vec<String>) {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    let ffmpeg_call = runner.calls().pop().unwrap();
+    (result, ffmpeg_call)
+}
+
+#[test]
+fn mp3_quality_uses_lame_vbr_levels() {
+    let mut job = convertion_job(AppMode::Audio, temp_output("vbr.mp3"));
+    job.audio_quality = Quality::Medium;
+    let (result, call) = run_audio_job(job);
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert!(has_args(&call, &[ "-vn", "-c:a", "libmp3lame", "-q:a", "5", "-ar", "24000" ]));
+}
+
+#[test]
+fn opus_quality_uses_bitrates_and_supported_sample_rates() {
+    let mut job = convertion_job(AppMode::Audio, temp_output("bitrate.opus"));
+    job.audio_codec = AudioCodec::Opus;
+    job.audio_quality = Quality::Good;
+    assert_eq!(job.output_extension(), "opus");
+    let (result, call) = run_audio_job(job);
+
+    assert_eq!(result.status, JobStatus::Finished);
+    // 90% of the assumed 128 kbit/s, the audio stream of the input does not report its bitrate
+    assert!(has_args(&call, &[ "-c:a", "libopus", "-b:a", "115K" ]));
+    // Opus does not support 32 kHz audio
+    assert!(!call.iter().any(|arg| arg == "-ar"));
+}
+
+#[test]
+fn flac_uses_the_compression_level_and_rejects_the_target_size() {
+    let mut job = convertion_job(AppMode::Audio, temp_output("lossless.flac"));
+    job.audio_codec = AudioCodec::Flac;
+    job.flac_compression = 8;
+    job.audio_quality = Quality::Poop;
+    let (result, call) = run_audio_job(job.clone());
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert!(has_args(&call, &[ "-c:a", "flac", "-compression_level", "8" ]));
+    assert!(!call.iter().any(|arg| arg == "-b:a" || arg == "-ar"));
+
+    job.output_file_size = Some(1.0);
+    let (result, _) = run_audio_job(job);
+    assert_eq!(result.status, JobStatus::Failed);
+}
+
+#[test]
+fn canceled_convertion_kills_ffmpeg_and_removes_the_output() {
+    let output = temp_output("canceled.mp4");
+    std::fs::write(&output, b"partial output").unwrap();
+
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", "out_time_us=1000000\nprogress=continue\n").hanging(),
+    ]);
+
+    let job = CompressorJob::Convert(Box::new(convertion_job(AppMode::Video, output.clone())));
+    let handle = job.start_with(runner.clone());
+
+    // Waiting for the first progress update, so the process is surely running
+    let Some(JobEvent::Progress(_)) = handle.wait_event(Duration::from_secs(10)) else {
+        panic!("expected a progress update");
+    };
+    handle.cancel();
+
+    let result = wait_for_job(&handle);
+    assert_eq!(result.status, JobStatus::Canceled);
+    assert_eq!(runner.kills.load(Ordering::SeqCst), 1);
+    assert!(!std::path::Path::new(&output).exists());
+}
+
+#[test]
+fn failing_ffmpeg_fails_the_job() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", "").failing(),
+    ]);
+
+    let job = CompressorJob::Convert(Box::new(convertion_job(AppMode::Audio, temp_output("failed.mp3"))));
+    let result = wait_for_job(&job.start_with(runner));
+
+    assert_eq!(result.status, JobStatus::Failed);
+    assert_eq!(result.messages[0].kind, MessageKind::Error);
+    assert_eq!(result.messages[0].text, "Convertion process failed");
+}
+
+#[test]
+fn missing_ffmpeg_fails_without_starting_anything() {
+    let runner = ScriptedRunner::without_programs(Vec::new());
+
+    let job = CompressorJob::Convert(Box::new(convertion_job(AppMode::Video, temp_output("missing.mp4"))));
+    let result = wait_for_job(&job.start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Failed);
+    assert!(runner.calls().is_empty());
+}
+
+#[test]
+fn process_that_can_not_be_started_fails_the_job() {
+    // Only ffprobe is scripted, so starting ffmpeg fails
+    let runner = ScriptedRunner::new(vec![ Script::new("ffprobe", FFPROBE_OUTPUT) ]);
+
+    let job = CompressorJob::Convert(Box::new(convertion_job(AppMode::Audio, temp_output("spawn.mp3"))));
+    let result = wait_for_job(&job.start_with(runner));
+
+    assert_eq!(result.status, JobStatus::Failed);
+    assert_eq!(result.messages[0].text, "Failed to start the the convertion process.");
+}
+
+#[test]
+fn download_reports_progress_and_speed() {
+    let runner = ScriptedRunner::new(vec![ Script::new("yt-dlp", YTDLP_OUTPUT) ]);
+
+    let job = DownloadJob {
+        input_link: String::from("https://example.com/watch"),
+        output_file: String::new(),
+    };
+    let result = wait_for_job(&CompressorJob::Download(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    let fractions: Vec<f32> = result.progress.iter().map(|update| update.fraction).collect();
+    assert_eq!(fractions, vec![ 0.25, 0.5, 1.0 ]);
+    assert_eq!(result.progress[0].download_speed, Some(524288.0));
+    assert_eq!(result.progress[0].current_file.as_deref(), Some("/tmp/video.mp4"));
+
+    let calls = runner.calls();
+    assert!(has_args(&calls[0], &[ "--progress-template" ]));
+    assert_eq!(calls[0].last().unwrap(), "https://example.com/watch");
+}
+
+fn video_bitrate(call: &[String]) -> u32 {
+    let index = call.iter().position(|arg| arg == "-b:v").unwrap();
+    call[index + 1].trim_end_matches('K').parse().unwrap()
+}
+
+#[test]
+fn oversized_output_is_encoded_again_with_a_lower_bitrate() {
+    let output = temp_output("retry.mp4");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(6_000_000),
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(4_500_000),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, output.clone());
+    job.output_file_size = Some(5.0);
+    job.verify_output_size = true;
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    let _ = std::fs::remove_file(&output);
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert_eq!(result.messages[0].kind, MessageKind::Success);
+
+    let attempts: Vec<u32> = result.progress.iter().filter_map(|update| update.size_attempt).collect();
+    assert_eq!(attempts, vec![ 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2 ]);
+
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 6);
+    assert!(video_bitrate(&calls[5]) < video_bitrate(&calls[2]));
+}
+
+#[test]
+fn size_verification_gives_up_after_the_last_attempt() {
+    let output = temp_output("too-large.mp4");
+    let mut scripts = Vec::new();
+    for _ in 0..MAX_SIZE_ATTEMPTS {
+        scripts.push(Script::new("ffprobe", FFPROBE_OUTPUT));
+        scripts.push(Script::new("ffmpeg", FFMPEG_OUTPUT));
+        scripts.push(Script::new("ffmpeg", FFMPEG_OUTPUT).writing(6_000_000));
+    }
+    let runner = ScriptedRunner::new(scripts);
+
+    let mut job = convertion_job(AppMode::Video, output.clone());
+    job.output_file_size = Some(5.0);
+    job.verify_output_size = true;
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    let _ = std::fs::remove_file(&output);
+
+    // The output is kept, but the user is warned about its size
+    assert_eq!(result.status, JobStatus::Finished);
+    assert_eq!(result.messages[0].kind, MessageKind::Warning);
+    assert_eq!(runner.calls().len(), 3 * MAX_SIZE_ATTEMPTS as usize);
+}
+
+#[test]
+fn size_attempts_reuse_the_measured_loudness_and_the_extracted_subtitles() {
+    let output = temp_output("normalized-retry.mp4");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_SUBTITLES_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).logging(LOUDNORM_LOG),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(6_000_000),
+        Script::new("ffprobe", FFPROBE_SUBTITLES_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(4_500_000),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, output.clone());
+    job.output_file_size = Some(5.0);
+    job.verify_output_size = true;
+    job.loudnorm = Some(Loudnorm::default());
+    job.subtitles.extract = SubtitleTracks::All;
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    let _ = std::fs::remove_file(&output);
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert_eq!(result.messages[0].kind, MessageKind::Success);
+
+    // The second attempt runs nothing but the two passes of the encoding
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 9);
+    assert!(!calls[7].iter().any(|arg| arg.starts_with("loudnorm")));
+    assert!(has_args(&calls[8], &[ "-af", "loudnorm=I=-23.0:TP=-1.0:LRA=7.0:measured_I=-27.61:measured_TP=-4.47:\
+measured_LRA=18.06:measured_thresh=-39.20:offset=0.10:linear=true,aresample=48000", &output ]));
+}
+
+#[test]
+fn frames_are_decoded_as_raw_rgba() {
+    // A 2x2 frame is 16 bytes of RGBA pixels
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffmpeg", "0123456789abcdef"),
+        Script::new("ffmpeg", "too short"),
+    ]);
+    let media_info = MediaInfo::from_ffprobe_json(FFPROBE_OUTPUT).unwrap();
+
+    let frame = extract_frame(runner.as_ref(), "input.mp4", &media_info, 1500, 2).unwrap();
+    assert_eq!((frame.width, frame.height, frame.time), (2, 2, 1500));
+    assert_eq!(frame.rgba, b"0123456789abcdef");
+
+    let call = &runner.calls()[0];
+    assert!(has_args(call, &[ "-ss", "1.500", "-i", "input.mp4", "-frames:v", "1", "-vf", "scale=2:2" ]));
+    assert!(has_args(call, &[ "-f", "rawvideo", "-pix_fmt", "rgba" ]));
+
+    // Incomplete frames are dropped
+    assert!(extract_frame(runner.as_ref(), "input.mp4", &media_info, 0, 2).is_none());
+    assert_eq!(thumbnail_times(10_000, 4), vec![ 1250, 3750, 6250, 8750 ]);
+}
+
+#[test]
+fn frames_of_rotated_videos_keep_the_displayed_shape() {
+    let phone = r#"{ "streams": [ {
+        "index": 0, "codec_type": "video", "width": 1920, "height": 1080,
+        "side_data_list": [ { "side_data_type": "Display Matrix", "rotation": -90 } ]
+    } ] }"#;
+    let media_info = MediaInfo::from_ffprobe_json(phone).unwrap();
+
+    // 1080 * 180 / 1920 is 101.25, the width is kept even
+    assert_eq!(frame_size(&media_info, 180), Some((100, 180)));
+
+    let runner = ScriptedRunner::new(vec![ Script::new("ffmpeg", "0123456789abcdef") ]);
+    assert!(extract_frame(runner.as_ref(), "input.mp4", &media_info, 0, 180).is_none());
+    assert!(has_args(&runner.calls()[0], &[ "-vf", "scale=100:180" ]));
+}
+
+#[test]
+fn loudness_is_measured_before_the_convertion() {
+    let output = temp_output("normalized.mp4");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).logging(LOUDNORM_LOG),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, output.clone());
+    job.loudnorm = Some(Loudnorm::default());
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    // The measuring pass covers the first half of the progress
+    let fractions: Vec<f32> = result.progress.iter().map(|update| update.fraction).collect();
+    assert_eq!(fractions, vec![ 0.125, 0.25, 0.5, 0.625, 0.75, 1.0 ]);
+
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 3);
+    assert!(has_args(&calls[1], &[ "-vn", "-af", "loudnorm=I=-23.0:TP=-1.0:LRA=7.0:print_format=json", "-f", "null" ]));
+
+    // The measured values are logged at the info level, which the quiet passes do not print
+    assert!(has_args(&calls[1], &[ "-loglevel", "info", "-vn" ]));
+
+    let filter = "loudnorm=I=-23.0:TP=-1.0:LRA=7.0:measured_I=-27.61:measured_TP=-4.47:measured_LRA=18.06:\
+measured_thresh=-39.20:offset=0.10:linear=true,aresample=48000";
+    assert!(has_args(&calls[2], &[ "-af", filter, &output ]));
+}
+
+#[test]
+fn loudness_without_the_measured_values_fails_the_job() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).logging("Output file is empty, nothing was encoded"),
+    ]);
+
+    let mut job = convertion_job(AppMode::Audio, temp_output("unmeasured.mp3"));
+    job.loudnorm = Some(Loudnorm::default());
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Failed);
+    assert_eq!(runner.calls().len(), 2);
+
+    // Silent audio has no loudness to normalize
+    let silent = LOUDNORM_LOG.replace("\"-27.61\"", "\"-inf\"");
+    assert!(LoudnessMeasurement::from_ffmpeg_output(&silent).is_none());
+    assert!(LoudnessMeasurement::from_ffmpeg_output(LOUDNORM_LOG).is_some());
+}
+
+#[test]
+fn subtitles_are_extracted_and_kept_in_the_container_format() {
+    let output = temp_output("subtitles.mp4");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_SUBTITLES_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, output.clone());
+    job.subtitles = Subtitles { keep: SubtitleTracks::Selected(vec![2]), burn: BurnIn::None, extract: SubtitleTracks::All };
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    // Text tracks are converted to SubRip, the image-based ones are copied
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 4);
+    assert!(has_args(&calls[1], &[ "-map", "0:2", "-c:s", "srt" ]));
+    assert_eq!(calls[1].last().unwrap(), &output.replace(".mp4", ".2.pol.srt"));
+    assert!(has_args(&calls[2], &[ "-map", "0:3", "-c:s", "copy" ]));
+    assert_eq!(calls[2].last().unwrap(), &output.replace(".mp4", ".3.eng.sup"));
+
+    assert!(has_args(&calls[3], &[ "-map", "0:v:0?", "-map", "0:a:0?", "-map", "0:2", "-c:s", "mov_text" ]));
+    assert_eq!(calls[3].last().unwrap(), &output);
+}
+
+#[test]
+fn burned_subtitles_follow_the_trim_and_are_scaled_with_the_video() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_SUBTITLES_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, temp_output("burned.mp4"));
+    job.subtitles.burn = BurnIn::Track(2);
+    job.trim.start = Some(1500);
+    job.scaling = Some(Scaling::short_side(480));
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    let filter = "setpts=PTS+1.500/TB,subtitles=input.mp4:si=0,setpts=PTS-STARTPTS,scale=854:480";
+    let call = runner.calls().pop().unwrap();
+    assert!(has_args(&call, &[ "-vf", filter, "-sn" ]));
+
+    // Special characters of the path are escaped for the filter
+    let subtitle_file = temp_output("subs:1.srt");
+    std::fs::write(&subtitle_file, "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_SUBTITLES_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Video, temp_output("burned-file.mp4"));
+    job.subtitles.burn = BurnIn::File(subtitle_file.clone());
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    let _ = std::fs::remove_file(&subtitle_file);
+
+    assert_eq!(result.status, JobStatus::Finished);
+    let call = runner.calls().pop().unwrap();
+    let vf = call.iter().position(|arg| arg == "-vf").unwrap();
+    assert!(call[vf + 1].starts_with("subtitles=/"));
+    assert!(call[vf + 1].ends_with("subs\\\\:1.srt"));
+}
+
+#[test]
+fn image_based_or_missing_subtitle_tracks_are_rejected() {
+    for subtitles in [
+        Subtitles { keep: SubtitleTracks::Selected(vec![3]), ..Default::default() },
+        Subtitles { burn: BurnIn::Track(3), ..Default::default() },
+        Subtitles { burn: BurnIn::Track(1), ..Default::default() },
+    ] {
+        let runner = ScriptedRunner::new(vec![ Script::new("ffprobe", FFPROBE_SUBTITLES_OUTPUT) ]);
+
+        let mut job = convertion_job(AppMode::Video, temp_output("image-subtitles.mp4"));
+        job.subtitles = subtitles;
+        let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+        assert_eq!(result.status, JobStatus::Failed);
+        assert_eq!(runner.calls().len(), 1);
+    }
+}
+
+#[test]
+fn thumbnail_saves_a_single_frame_from_the_middle() {
+    let output = temp_output("frame.jpg");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Thumbnail, output.clone());
+    job.thumbnail_options = ThumbnailOptions { kind: ThumbnailKind::Frame(None), format: ImageFormat::Jpeg, width: 640 };
+    assert_eq!(job.output_extension(), "jpg");
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    let call = runner.calls().pop().unwrap();
+    assert!(has_args(&call, &[ "-ss", "5.000", "-i", "input.mp4", "-y", "-frames:v", "1", "-vf", "scale=640:-2" ]));
+    assert!(has_args(&call, &[ "-c:v", "mjpeg", "-q:v", "2", &output ]));
+}
+
+#[test]
+fn contact_sheet_tiles_evenly_spaced_frames() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Thumbnail, temp_output("sheet.png"));
+    job.thumbnail_options.kind = ThumbnailKind::ContactSheet { columns: 2, rows: 2, timestamps: true };
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    // Every input is seeked to the middle of its part of the video
+    let call = runner.calls().pop().unwrap();
+    for time in [ "1.250", "3.750", "6.250", "8.750" ] {
+        assert!(has_args(&call, &[ "-ss", time, "-i", "input.mp4" ]));
+    }
+
+    let filter = &call[call.iter().position(|arg| arg == "-filter_complex").unwrap() + 1];
+    assert!(filter.starts_with("[0:v]trim=end_frame=1,scale=320:-2,drawtext=text=00\\\\:00\\\\:01:"));
+    assert!(filter.ends_with("[f0][f1][f2][f3]concat=n=4:v=1:a=0,tile=2x2:padding=4:margin=4"));
+    assert!(has_args(&call, &[ "-frames:v", "1", "-c:v", "png" ]));
+
+    // Audio files have nothing to take the thumbnail of
+    let audio_probe = r#"{
+        "streams": [ { "index": 0, "codec_type": "audio", "codec_name": "mp3", "sample_rate": "44100" } ],
+        "format": { "duration": "10.000000" }
+    }"#;
+    let runner = ScriptedRunner::new(vec![ Script::new("ffprobe", audio_probe) ]);
+    let job = convertion_job(AppMode::Thumbnail, temp_output("audio-sheet.png"));
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    assert_eq!(result.status, JobStatus::Failed);
+}
+
+#[test]
+fn gif_is_encoded_with_the_palette_of_the_trimmed_part() {
+    let output = temp_output("clip.gif");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(768),
+        Script::new("ffmpeg", FFMPEG_OUTPUT),
+    ]);
+
+    let mut job = convertion_job(AppMode::Animation, output.clone());
+    job.trim = Trim { start: Some(2000), end: Some(6000), cut_mode: CutMode::Fast };
+    job.animation_options.plays = 1;
+    assert_eq!(job.output_extension(), "gif");
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    let calls = runner.calls();
+    assert_eq!(calls.len(), 3);
+
+    // The palette is generated out of the trimmed part only
+    let palette = calls[1].last().unwrap().clone();
+    assert!(has_args(&calls[1], &[ "-ss", "2.000", "-i", "input.mp4", "-y", "-t", "4.000" ]));
+    assert!(has_args(&calls[1], &[ "-vf", "fps=15,scale='min(480,iw)':-2:flags=lanczos,palettegen=stats_mode=diff" ]));
+
+    assert!(has_args(&calls[2], &[ "-ss", "2.000", "-i", "input.mp4", "-i", &palette, "-y", "-t", "4.000" ]));
+    assert!(has_args(&calls[2], &[
+        "-lavfi", "fps=15,scale='min(480,iw)':-2:flags=lanczos[frames];[frames][1:v]paletteuse=dither=sierra2_4a:diff_mode=rectangle",
+    ]));
+    assert!(has_args(&calls[2], &[ "-loop", "-1", &output ]));
+
+    assert_eq!(result.progress.last().unwrap().fraction, 1.0);
+    assert!(!std::path::Path::new(&palette).exists());
+}
+
+#[test]
+fn animation_gets_smaller_until_it_fits_the_selected_size() {
+    let output = temp_output("clip.webp");
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(8_000_000),
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(500_000),
+    ]);
+
+    // Animations are always verified, there is no bitrate to pick the size with
+    let mut job = convertion_job(AppMode::Animation, output.clone());
+    job.animation_options.format = AnimationFormat::Webp;
+    job.animation_options.fps = 60;
+    job.output_file_size = Some(1.0);
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+    let _ = std::fs::remove_file(&output);
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert_eq!(result.messages[0].kind, MessageKind::Success);
+
+    // The frame rate of the source is never exceeded
+    let calls = runner.calls();
+    assert!(has_args(&calls[1], &[ "-vf", "fps=30,scale='min(480,iw)':-2:flags=lanczos" ]));
+    assert!(has_args(&calls[1], &[ "-c:v", "libwebp_anim" ]));
+    assert!(has_args(&calls[3], &[ "-vf", "fps=15,scale='min(236,iw)':-2:flags=lanczos" ]));
+    assert!(has_args(&calls[3], &[ "-loop", "0", &output ]));
+}
+
+#[test]
+fn frame_rate_is_changed_in_both_passes() {
+    let run = |frame_rate: FrameRate| {
+        let runner = ScriptedRunner::new(vec![
+            Script::new("ffprobe", FFPROBE_OUTPUT),
+            Script::new("ffmpeg", FFMPEG_OUTPUT),
+            Script::new("ffmpeg", FFMPEG_OUTPUT),
+        ]);
+
+        let mut job = convertion_job(AppMode::Video, temp_output("frame-rate.mp4"));
+        job.output_file_size = Some(5.0);
+        job.frame_rate = frame_rate;
+        let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+        assert_eq!(result.status, JobStatus::Finished);
+        runner.calls()
+    };
+
+    let calls = run(FrameRate { target: FrameRateTarget::Cap(24.0), ..Default::default() });
+    assert!(has_args(&calls[1], &[ "-vf", "fps=24" ]));
+    assert!(has_args(&calls[2], &[ "-vf", "fps=24" ]));
+
+    // The 30 fps source is under the cap, the constant frame rate uses its average frame rate
+    let calls = run(FrameRate { target: FrameRateTarget::Cap(60.0), constant: true, ..Default::default() });
+    assert!(!calls[2].iter().any(|arg| arg == "-vf"));
+    assert!(has_args(&calls[1], &[ "-r", "30", "-fps_mode", "cfr" ]));
+    assert!(has_args(&calls[2], &[ "-r", "30", "-fps_mode", "cfr" ]));
+
+    let calls = run(FrameRate { target: FrameRateTarget::Convert(12.5), method: FrameRateMethod::Drop, constant: false });
+    assert!(!calls[2].iter().any(|arg| arg == "-vf"));
+    assert!(has_args(&calls[1], &[ "-r", "12.5", "-fps_mode", "vfr" ]));
+    assert!(has_args(&calls[2], &[ "-r", "12.5", "-fps_mode", "vfr" ]));
+}
+
+#[test]
+fn black_bars_are_detected_and_cropped_before_the_scaling() {
+    let mut scripts = vec![ Script::new("ffprobe", FFPROBE_OUTPUT) ];
+    scripts.push(Script::new("ffmpeg", "").logging(CROPDETECT_BLACK_LOG));
+    scripts.extend((1..CROP_SAMPLES).map(|_| Script::new("ffmpeg", "").logging(CROPDETECT_LOG)));
+    scripts.push(Script::new("ffmpeg", FFMPEG_OUTPUT));
+    let runner = ScriptedRunner::new(scripts);
+
+    let mut job = convertion_job(AppMode::Video, temp_output("cropped.mp4"));
+    job.crop = Crop::Detect;
+    job.scaling = Some(Scaling::short_side(360));
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    // The parts are spread evenly over the 10 s of the video
+    let calls = runner.calls();
+    assert_eq!(calls.len(), CROP_SAMPLES + 2);
+    assert!(has_args(&calls[1], &[ "-ss", "0.833", "-t", "1.666", "-i", "input.mp4" ]));
+    assert!(has_args(&calls[CROP_SAMPLES], &[ "-ss", "9.166" ]));
+
+    // The black part is skipped, the picture is scaled by its short side after the crop
+    assert!(has_args(&calls[CROP_SAMPLES + 1], &[ "-vf", "crop=1280:544:0:88,scale=848:360" ]));
+}
+
+#[test]
+fn selected_crop_is_checked_against_the_video() {
+    let run = |crop: Crop, trim: Trim| {
+        let runner = ScriptedRunner::new(vec![
+            Script::new("ffprobe", FFPROBE_OUTPUT),
+            Script::new("ffmpeg", FFMPEG_OUTPUT),
+        ]);
+
+        let mut job = convertion_job(AppMode::Video, temp_output("crop-rect.mp4"));
+        job.crop = crop;
+        job.trim = trim;
+        let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+        (result, runner.calls())
+    };
+
+    // Nothing to crop, no filter is added
+    let (result, calls) = run(Crop::Rect(CropRect { width: 1280, height: 720, x: 0, y: 0 }), Trim::default());
+    assert_eq!(result.status, JobStatus::Finished);
+    assert!(!calls[1].iter().any(|arg| arg == "-vf"));
+
+    let (result, calls) = run(Crop::Rect(CropRect { width: 1280, height: 544, x: 0, y: 200 }), Trim::default());
+    assert_eq!(result.status, JobStatus::Failed);
+    assert_eq!(calls.len(), 1);
+
+    let fast_cut = Trim { start: Some(1000), end: None, cut_mode: CutMode::Fast };
+    let (result, _) = run(Crop::Detect, fast_cut);
+    assert_eq!(result.status, JobStatus::Failed);
+    assert!(result.messages[0].text.contains("black bars"));
+}
+
+/// Starts the Dual Mode job and cancels it once the progress reaches `cancel_at`. Returns the result
+/// (with all of the progress updates) and the calls of the runner.
+fn cancel_dual_job(scripts: Vec<Script>, output_file: &str, cancel_at: f32) -> (JobResult, Vec<Vec<String>>) {
+    let runner = ScriptedRunner::new(scripts);
+
+    let convertion = convertion_job(AppMode::Video, output_file.to_string());
+    let output_dir = std::env::temp_dir();
+    let job = DualJob { input_link: String::from("https://example.com/watch"), convertion, output_dir };
+    let handle = CompressorJob::Dual(Box::new(job)).start_with(runner.clone());
+
+    let mut progress = Vec::new();
+    let start = Instant::now();
+    while !progress.last().is_some_and(|update: &WorkProgress| update.fraction >= cancel_at) {
+        assert!(start.elapsed() < Duration::from_secs(10), "the job did not reach the progress in time");
+        match handle.wait_event(Duration::from_millis(100)) {
+            Some(JobEvent::Progress(update)) => progress.push(update),
+            Some(JobEvent::Finished(status)) => panic!("the job finished before it was canceled: {status:?}"),
+            _ => {}
+        }
+    }
+    handle.cancel();
+
+    let mut result = wait_for_job(&handle);
+    progress.append(&mut result.progress);
+    result.progress = progress;
+    (result, runner.calls())
+}
+
+fn assert_steady_progress(progress: &[WorkProgress]) {
+    assert!(progress.windows(2).all(|pair| pair[0].fraction <= pair[1].fraction));
+}
+
+#[test]
+fn dual_job_canceled_while_downloading_removes_the_download() {
+    let output = temp_output("dual-download.mp4");
+    let (result, calls) = cancel_dual_job(
+        vec![ Script::new("yt-dlp", YTDLP_OUTPUT).downloading(1000).hanging() ], &output, 0.5,
+    );
+
+    assert_eq!(result.status, JobStatus::Canceled);
+    assert_eq!(calls.len(), 1);
+
+    // The download covers the first half of the progress
+    assert_steady_progress(&result.progress);
+    assert!(result.progress.iter().all(|update| update.fraction <= 0.5));
+
+    assert!(!download_dir(&calls[0]).unwrap().exists());
+    assert!(!std::path::Path::new(&output).exists());
+}
+
+#[test]
+fn dual_job_canceled_while_converting_removes_the_download_and_the_output() {
+    let output = temp_output("dual-convert.mp4");
+    let (result, calls) = cancel_dual_job(
+        vec![
+            Script::new("yt-dlp", YTDLP_OUTPUT).downloading(1000),
+            Script::new("ffprobe", FFPROBE_OUTPUT),
+            Script::new("ffmpeg", "out_time_us=5000000\nprogress=continue\n").writing(500).hanging(),
+        ],
+        &output,
+        0.75,
+    );
+
+    assert_eq!(result.status, JobStatus::Canceled);
+    assert_eq!(calls.len(), 3);
+    assert!(calls[2].iter().any(|arg| arg.ends_with("video.mp4")));
+
+    // The download fills the first half, the convertion continues from there
+    assert_steady_progress(&result.progress);
+    assert!(result.progress.iter().any(|update| update.fraction == 0.5));
+    assert_eq!(result.progress.last().unwrap().fraction, 0.75);
+
+    assert!(!download_dir(&calls[0]).unwrap().exists());
+    assert!(!std::path::Path::new(&output).exists());
+}
+
+#[test]
+fn batch_into_the_input_directory_is_rejected() {
+    let dir = std::env::temp_dir().join(format!("compressor-batch-{}-same-dir", std::process::id()));
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("a.mp4"), b"").unwrap();
+
+    let template = convertion_job(AppMode::Video, String::new());
+    let same_dir = dir.join("sub/..").to_string_lossy().to_string();
+    let result = batch_jobs(&template, &dir, &same_dir, "mp4", false);
+    assert_eq!(result.err(), Some("Output directory must be different from the input directory."));
+
+    let jobs = batch_jobs(&template, &dir, &dir.join("out").to_string_lossy(), "mp4", false).unwrap();
+    assert_eq!(jobs[0].output_file, dir.join("out/a.mp4").to_string_lossy());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn process_without_the_standard_output_still_finishes() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).without_stdout(),
+    ]);
+
+    let job = CompressorJob::Convert(Box::new(convertion_job(AppMode::Audio, temp_output("no-stdout.mp3"))));
+    let result = wait_for_job(&job.start_with(runner));
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert!(result.progress.is_empty());
+}
+
+#[test]
+fn thumbnail_is_not_encoded_again_for_the_size() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(2_000_000),
+    ]);
+
+    let mut job = convertion_job(AppMode::Thumbnail, temp_output("large-frame.png"));
+    job.output_file_size = Some(1.0);
+    job.verify_output_size = true;
+    let result = wait_for_job(&CompressorJob::Convert(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+    assert_eq!(runner.calls().len(), 2);
+    assert_eq!(result.messages[0].kind, MessageKind::Success);
+}
+
+#[test]
+fn dual_job_without_the_output_saves_into_the_output_directory() {
+    let runner = ScriptedRunner::new(vec![
+        Script::new("yt-dlp", YTDLP_OUTPUT).downloading(1000),
+        Script::new("ffprobe", FFPROBE_OUTPUT),
+        Script::new("ffmpeg", FFMPEG_OUTPUT).writing(500),
+    ]);
+
+    let output_dir = PathBuf::from(temp_output("dual-output-dir"));
+    std::fs::create_dir_all(&output_dir).unwrap();
+
+    let convertion = convertion_job(AppMode::Video, String::new());
+    let job = DualJob { input_link: String::from("https://example.com/watch"), convertion, output_dir: output_dir.clone() };
+    let result = wait_for_job(&CompressorJob::Dual(Box::new(job)).start_with(runner.clone()));
+
+    assert_eq!(result.status, JobStatus::Finished);
+
+    // The output is named after the downloaded file
+    let output = output_dir.join("video-new.mp4");
+    assert_eq!(runner.calls()[2].last().unwrap(), &output.to_string_lossy());
+    assert!(output.exists());
+    let _ = std::fs::remove_dir_all(&output_dir);
+}
@@ -0,0 +1,122 @@
+use compressor::media_info::{MediaInfo, StreamKind};
+
+const VIDEO_PROBE: &str = r#"{
+    "streams": [
+        {
+            "index": 0,
+            "codec_name": "h264",
+            "codec_long_name": "H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10",
+            "codec_type": "video",
+            "width": 1920,
+            "height": 1080,
+            "r_frame_rate": "30000/1001",
+            "avg_frame_rate": "30000/1001",
+            "duration": "12.345000",
+            "bit_rate": "4500000",
+            "tags": { "language": "und", "handler_name": "VideoHandler" }
+        },
+        {
+            "index": 1,
+            "codec_name": "aac",
+            "codec_type": "audio",
+            "sample_rate": "48000",
+            "channels": 2,
+            "r_frame_rate": "0/0",
+            "avg_frame_rate": "0/0",
+            "duration": "12.352000",
+            "bit_rate": "128000",
+            "tags": { "language": "eng" }
+        },
+        {
+            "index": 2,
+            "codec_name": "mov_text",
+            "codec_type": "subtitle",
+            "tags": { "language": "pol", "title": "Polish" }
+        }
+    ],
+    "format": {
+        "filename": "input.mp4",
+        "nb_streams": 3,
+        "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+        "format_long_name": "QuickTime / MOV",
+        "duration": "12.352000",
+        "size": "7000000",
+        "bit_rate": "4533678",
+        "tags": { "title": "Some title" }
+    }
+}"#;
+
+#[test]
+fn parses_streams_and_format() {
+    let info = MediaInfo::from_ffprobe_json(VIDEO_PROBE).unwrap();
+
+    assert_eq!(info.format_name, "mov,mp4,m4a,3gp,3g2,mj2");
+    assert_eq!(info.duration(), Some(12352));
+    assert_eq!(info.bit_rate, Some(4533678));
+    assert_eq!(info.size, Some(7000000));
+    assert_eq!(info.tags.get("title").map(String::as_str), Some("Some title"));
+    assert_eq!(info.streams.len(), 3);
+
+    let video = info.first_video().unwrap();
+    assert_eq!(video.codec_name, "h264");
+    assert_eq!((video.width, video.height), (Some(1920), Some(1080)));
+    assert!((video.frame_rate().unwrap() - 29.97).abs() < 0.01);
+    assert_eq!(video.bit_rate, Some(4500000));
+
+    let audio = info.first_audio().unwrap();
+    assert_eq!(audio.sample_rate, Some(48000));
+    assert_eq!(audio.channels, Some(2));
+    assert_eq!(audio.frame_rate(), None);
+    assert_eq!(audio.language(), Some("eng"));
+
+    let subtitle = info.subtitle_streams().next().unwrap();
+    assert_eq!(subtitle.kind, StreamKind::Subtitle);
+    assert_eq!(subtitle.index, 2);
+    assert_eq!(subtitle.title(), Some("Polish"));
+}
+
+#[test]
+fn handles_unknown_and_long_durations() {
+    let unknown = r#"{ "streams": [], "format": { "format_name": "hls", "duration": "N/A", "bit_rate": "N/A" } }"#;
+    let info = MediaInfo::from_ffprobe_json(unknown).unwrap();
+    assert_eq!(info.duration(), None);
+    assert_eq!(info.bit_rate, None);
+
+    // 120 hours, more than the two hour digits printed by "ffmpeg -i"
+    let long = r#"{ "streams": [], "format": { "duration": "432000.500000" } }"#;
+    let info = MediaInfo::from_ffprobe_json(long).unwrap();
+    assert_eq!(info.duration(), Some(432000500));
+
+    // Without the format duration the longest stream is used
+    let stream_only = r#"{ "streams": [ { "index": 0, "codec_type": "audio", "duration": "3.5" } ] }"#;
+    let info = MediaInfo::from_ffprobe_json(stream_only).unwrap();
+    assert_eq!(info.duration(), Some(3500));
+}
+
+#[test]
+fn reads_rotation_and_variable_frame_rate() {
+    let phone = r#"{ "streams": [ {
+        "index": 0, "codec_type": "video", "width": 1920, "height": 1080,
+        "r_frame_rate": "60/1", "avg_frame_rate": "2850000/59401",
+        "side_data_list": [ { "side_data_type": "Display Matrix", "rotation": -90 } ]
+    } ] }"#;
+    let video = MediaInfo::from_ffprobe_json(phone).unwrap().streams.remove(0);
+    assert_eq!(video.rotation, -90);
+    assert_eq!(video.display_size(), Some((1080, 1920)));
+    assert!(video.is_variable_frame_rate());
+
+    // Older ffprobe prints the rotation in the tags
+    let tagged = r#"{ "streams": [ {
+        "index": 0, "codec_type": "video", "width": 1920, "height": 1080,
+        "r_frame_rate": "30/1", "avg_frame_rate": "30/1", "tags": { "rotate": "180" }
+    } ] }"#;
+    let video = MediaInfo::from_ffprobe_json(tagged).unwrap().streams.remove(0);
+    assert_eq!(video.display_size(), Some((1920, 1080)));
+    assert!(!video.is_variable_frame_rate());
+}
+
+#[test]
+fn rejects_invalid_output() {
+    assert!(MediaInfo::from_ffprobe_json("").is_none());
+    assert!(MediaInfo::from_ffprobe_json("Duration: 00:00:12.35").is_none());
+}
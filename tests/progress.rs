@@ -0,0 +1,113 @@
+use std::{io::{BufReader, Read}, time::Duration};
+
+use compressor::progress::{ProgressReader, DownloadProgress, format_duration, format_size};
+
+const FFMPEG_PROGRESS: &str = "\
+frame=120
+fps=59.94
+stream_0_0_q=28.0
+bitrate= 812.5kbits/s
+total_size=262192
+out_time_us=2002000
+out_time_ms=2002000
+out_time=00:00:02.002000
+dup_frames=0
+drop_frames=0
+speed=1.98x
+progress=continue
+frame=240
+fps=60.01
+stream_0_0_q=-1.0
+bitrate=N/A
+total_size=524336
+out_time_us=4004000
+out_time_ms=4004000
+out_time=00:00:04.004000
+dup_frames=0
+drop_frames=0
+speed=N/A
+progress=end
+";
+
+/// Returns at most 7 bytes per read, so most of the lines get split between reads
+struct ChunkedReader<'a>(&'a [u8]);
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let len = self.0.len().min(buf.len()).min(7);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        Ok(len)
+    }
+}
+
+#[test]
+fn parses_every_progress_block() {
+    let reader = BufReader::new(ChunkedReader(FFMPEG_PROGRESS.as_bytes()));
+    let blocks: Vec<_> = ProgressReader::new(reader).collect();
+    assert_eq!(blocks.len(), 2);
+
+    let first = &blocks[0];
+    assert_eq!(first.out_time, Duration::from_micros(2002000));
+    assert_eq!(first.frame, Some(120));
+    assert_eq!(first.fps, Some(59.94));
+    assert_eq!(first.bitrate, Some(812.5));
+    assert_eq!(first.total_size, Some(262192));
+    assert_eq!(first.speed, Some(1.98));
+    assert!(!first.is_end);
+
+    let last = &blocks[1];
+    assert_eq!(last.out_time, Duration::from_micros(4004000));
+    assert_eq!(last.frame, Some(240));
+    assert_eq!(last.bitrate, None);
+    assert_eq!(last.speed, None);
+    assert!(last.is_end);
+}
+
+#[test]
+fn ignores_incomplete_block() {
+    let reader = BufReader::new("frame=1\nout_time_us=1000\n".as_bytes());
+    assert_eq!(ProgressReader::new(reader).count(), 0);
+}
+
+#[test]
+fn formats_durations() {
+    assert_eq!(format_duration(Duration::from_secs(0)), "00:00:00");
+    assert_eq!(format_duration(Duration::from_secs(3723)), "01:02:03");
+    assert_eq!(format_duration(Duration::from_secs(360000)), "100:00:00");
+}
+
+#[test]
+fn parses_ytdlp_progress_lines() {
+    let line = "compressor-progress downloading 1048576 4194304 NA 524288.5 6 NA NA NA NA /tmp/Some video [abc].mp4";
+    let progress = DownloadProgress::parse_line(line).unwrap();
+    assert!(!progress.is_finished);
+    assert_eq!(progress.downloaded_bytes, 1048576);
+    assert_eq!(progress.total_bytes, Some(4194304));
+    assert_eq!(progress.speed, Some(524288.5));
+    assert_eq!(progress.eta, Some(Duration::from_secs(6)));
+    assert_eq!(progress.filename, "/tmp/Some video [abc].mp4");
+    assert_eq!(progress.fraction(), Some(0.25));
+
+    // Fragmented download without known size, second file out of four in a playlist
+    let line = "compressor-progress downloading 1000 NA NA NA NA 5 10 2 4 part.mp4";
+    let progress = DownloadProgress::parse_line(line).unwrap();
+    assert_eq!(progress.total_bytes, None);
+    assert_eq!(progress.file_fraction(), Some(0.5));
+    assert_eq!(progress.fraction(), Some(0.375));
+
+    let line = "compressor-progress finished 4194304 4194304 NA NA NA NA NA NA NA done.mp4";
+    let progress = DownloadProgress::parse_line(line).unwrap();
+    assert!(progress.is_finished);
+    assert_eq!(progress.fraction(), Some(1.0));
+
+    assert!(DownloadProgress::parse_line("[download]  12.5% of 4.00MiB at 512.00KiB/s ETA 00:06").is_none());
+    assert!(DownloadProgress::parse_line("compressor-progress downloading 1").is_none());
+}
+
+#[test]
+fn formats_sizes() {
+    assert_eq!(format_size(512.0), "512.00 B");
+    assert_eq!(format_size(1536.0), "1.50 KiB");
+    assert_eq!(format_size(5.0 * 1024.0 * 1024.0), "5.00 MiB");
+}
@@ -0,0 +1,60 @@
+use compressor::engine::scale::{ScaleFit, ScaleSize, Scaling};
+use compressor::media_info::{MediaInfo, StreamInfo};
+
+fn video(width: u32, height: u32, rotation: i32) -> StreamInfo {
+    let json = format!(r#"{{
+        "streams": [ {{
+            "index": 0, "codec_type": "video", "width": {width}, "height": {height},
+            "side_data_list": [ {{ "side_data_type": "Display Matrix", "rotation": {rotation} }} ]
+        }} ]
+    }}"#);
+    MediaInfo::from_ffprobe_json(&json).unwrap().streams.remove(0)
+}
+
+fn fitted(width: u32, height: u32, fit: ScaleFit, upscale: bool) -> Scaling {
+    Scaling { size: ScaleSize::Box { width, height }, fit, upscale }
+}
+
+#[test]
+fn short_side_is_scaled_to_even_dimensions() {
+    let scaling = Scaling::short_side(720);
+    assert_eq!(scaling.filter(&video(1920, 1080, 0)).as_deref(), Some("scale=1280:720"));
+    assert_eq!(scaling.filter(&video(1080, 1920, 0)).as_deref(), Some("scale=720:1280"));
+
+    // Phones store the vertical videos as rotated horizontal ones
+    assert_eq!(scaling.filter(&video(1920, 1080, -90)).as_deref(), Some("scale=720:1280"));
+
+    // 1280 * 480 / 720 is 853.33, libx264 needs an even width
+    let filter = Scaling::short_side(480).filter(&video(1280, 720, 0));
+    assert_eq!(filter.as_deref(), Some("scale=854:480"));
+}
+
+#[test]
+fn smaller_videos_are_scaled_up_only_when_asked() {
+    assert_eq!(Scaling::short_side(1080).filter(&video(640, 360, 0)), None);
+    assert_eq!(Scaling::short_side(360).filter(&video(640, 360, 0)), None);
+
+    let upscaled = Scaling { upscale: true, ..Scaling::short_side(1080) };
+    assert_eq!(upscaled.filter(&video(640, 360, 0)).as_deref(), Some("scale=1920:1080"));
+
+    let padded = fitted(1280, 720, ScaleFit::Pad, false).filter(&video(640, 360, 0));
+    assert_eq!(padded.as_deref(), Some("pad=1280:720:(ow-iw)/2:(oh-ih)/2"));
+    assert_eq!(fitted(1280, 720, ScaleFit::Fill, false).filter(&video(640, 360, 0)), None);
+}
+
+#[test]
+fn video_is_fitted_filled_or_padded_into_the_custom_size() {
+    let source = video(1920, 1080, 0);
+
+    let fit = fitted(1280, 1024, ScaleFit::Fit, false).filter(&source);
+    assert_eq!(fit.as_deref(), Some("scale=1280:720"));
+
+    let fill = fitted(1280, 1024, ScaleFit::Fill, false).filter(&source);
+    assert_eq!(fill.as_deref(), Some("scale=1820:1024,crop=1280:1024"));
+
+    let pad = fitted(1280, 1024, ScaleFit::Pad, false).filter(&source);
+    assert_eq!(pad.as_deref(), Some("scale=1280:720,pad=1280:1024:(ow-iw)/2:(oh-ih)/2"));
+
+    assert!(fitted(1280, 721, ScaleFit::Fit, false).validate().is_err());
+    assert!(Scaling::short_side(8).validate().is_err());
+}
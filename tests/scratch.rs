@@ -52,15 +52,4 @@ mod tests {
             println!("Failed to aquire the lock, SHIT!");
         }
     }
-
-    #[test]
-    fn extraction_test() {
-        let Some(info) = compressor::get_media_info("suckmynuts.mp3") else {
-            panic!("rip");
-
-        };
-        println!("info is: {info}");
-        let duration = compressor::extract_media_duration(&info);
-        println!("duration in: {duration:?}ms");
-    }
 }
@@ -0,0 +1,35 @@
+use compressor::engine::trim::{format_time, parse_time, CutMode, Trim};
+
+#[test]
+fn parses_seconds_and_clock_times() {
+    assert_eq!(parse_time("90"), Some(90_000));
+    assert_eq!(parse_time("1.5"), Some(1500));
+    assert_eq!(parse_time("1:30"), Some(90_000));
+    assert_eq!(parse_time("01:02:03.250"), Some(3_723_250));
+    assert_eq!(parse_time(" 0:05 "), Some(5000));
+}
+
+#[test]
+fn rejects_malformed_times() {
+    assert_eq!(parse_time(""), None);
+    assert_eq!(parse_time("abc"), None);
+    assert_eq!(parse_time("-5"), None);
+    assert_eq!(parse_time("1:75"), None);
+    assert_eq!(parse_time("1:2:3:4"), None);
+    assert_eq!(parse_time(&format_time(3_723_250)), Some(3_723_250));
+}
+
+#[test]
+fn trimmed_duration_stays_inside_the_media() {
+    let trim = Trim { start: Some(10_000), end: Some(40_000), cut_mode: CutMode::Accurate };
+    assert_eq!(trim.trimmed_duration(60_000), Ok(30_000));
+    assert_eq!(trim.trimmed_duration(25_000), Ok(15_000));
+    assert!(trim.trimmed_duration(5_000).is_err());
+
+    let trim = Trim { start: None, end: Some(20_000), cut_mode: CutMode::Accurate };
+    assert_eq!(trim.trimmed_duration(60_000), Ok(20_000));
+    assert_eq!(Trim::default().trimmed_duration(60_000), Ok(60_000));
+
+    let backwards = Trim { start: Some(20_000), end: Some(10_000), cut_mode: CutMode::Accurate };
+    assert!(backwards.validate().is_err());
+}